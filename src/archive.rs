@@ -0,0 +1,268 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::dates::days_since_epoch;
+
+/// The most a GNU long name or pax header is read into memory for, far more than any
+/// real path needs.
+const MAX_LONG_NAME: u64 = 1024 * 1024;
+
+/// A file or directory stored in an archive, with its path relative to the archive root.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn format_of(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    format_of(path).is_some()
+}
+
+/// Splits a path like `/tmp/src.zip/lib` into the archive file and the directory inside
+/// it, or returns None for ordinary paths.
+pub fn split_archive_path(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    if path.is_dir() {
+        return None;
+    }
+    let archive = path
+        .ancestors()
+        .find(|ancestor| is_archive(ancestor) && ancestor.is_file())?;
+    let inner = path.strip_prefix(archive).ok()?;
+    Some((archive.to_path_buf(), inner.to_path_buf()))
+}
+
+/// Lists every entry of a zip or tar archive. Nothing is extracted.
+pub fn read_archive(archive: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    match format_of(archive) {
+        Some(Format::Zip) => read_zip(File::open(archive)?),
+        Some(Format::Tar) => {
+            let file = File::open(archive)?;
+            let length = file.metadata()?.len();
+            read_tar(file, Some(length))
+        }
+        Some(Format::TarGz) => {
+            // there is no inflate in std, so gzip does the decompressing
+            let mut gzip = Command::new("gzip")
+                .arg("-dc")
+                .arg(archive)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()?;
+            let entries = gzip.stdout.take().map(|stdout| read_tar(stdout, None));
+            let status = gzip.wait()?;
+            match entries {
+                Some(Ok(entries)) if status.success() => Ok(entries),
+                Some(Err(e)) => Err(e),
+                _ => Err(invalid("gzip could not decompress the archive")),
+            }
+        }
+        None => Err(invalid("not an archive")),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Drops `./` prefixes and anything that would climb out of the archive root.
+fn clean_path(name: &str) -> PathBuf {
+    Path::new(name)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Reads the central directory at the end of the file, which lists every entry.
+fn read_zip(mut file: File) -> io::Result<Vec<ArchiveEntry>> {
+    // the end record is 22 bytes plus a comment of up to 64 KiB
+    let length = file.seek(SeekFrom::End(0))?;
+    let tail_length = length.min(22 + 0xFFFF);
+    file.seek(SeekFrom::Start(length - tail_length))?;
+    let mut tail = vec![];
+    file.read_to_end(&mut tail)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&at| u32_at(&tail, at) == 0x0605_4b50)
+        .ok_or_else(|| invalid("no zip end of central directory"))?;
+    let count = u16_at(&tail, end + 10) as usize;
+    let directory_size = u32_at(&tail, end + 12) as u64;
+    let directory_offset = u32_at(&tail, end + 16) as u64;
+    // the sizes come from the file, so they are checked before anything is allocated
+    if directory_offset
+        .checked_add(directory_size)
+        .is_none_or(|directory_end| directory_end > length)
+    {
+        return Err(invalid("zip central directory is past the end of the file"));
+    }
+
+    let mut directory = vec![0; directory_size as usize];
+    file.seek(SeekFrom::Start(directory_offset))?;
+    file.read_exact(&mut directory)?;
+
+    let mut entries = vec![];
+    let mut at = 0;
+    for _ in 0..count {
+        if at + 46 > directory.len() || u32_at(&directory, at) != 0x0201_4b50 {
+            return Err(invalid("corrupt zip central directory"));
+        }
+        let name_length = u16_at(&directory, at + 28) as usize;
+        let extra_length = u16_at(&directory, at + 30) as usize;
+        let comment_length = u16_at(&directory, at + 32) as usize;
+        let Some(name) = directory.get(at + 46..at + 46 + name_length) else {
+            return Err(invalid("corrupt zip central directory"));
+        };
+        let name = String::from_utf8_lossy(name);
+        entries.push(ArchiveEntry {
+            path: clean_path(&name),
+            is_dir: name.ends_with('/'),
+            size: u32_at(&directory, at + 24) as u64,
+            modified: dos_time(u16_at(&directory, at + 14), u16_at(&directory, at + 12)),
+        });
+        at += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(entries)
+}
+
+/// Zip stores local time without a zone, which is shown as if it were UTC.
+fn dos_time(date: u16, time: u16) -> SystemTime {
+    let (year, month, day) = (
+        1980 + (date >> 9) as i64,
+        (date >> 5 & 0xF) as i64,
+        (date & 0x1F) as i64,
+    );
+    let days = days_since_epoch(year, month.max(1), day.max(1));
+    let seconds = days * 86_400
+        + (time >> 11) as i64 * 3600
+        + (time >> 5 & 0x3F) as i64 * 60
+        + (time & 0x1F) as i64 * 2;
+    UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)
+}
+
+/// Walks the 512-byte ustar headers, following GNU and pax long names. `length` is the
+/// size of the archive when it is known, which no entry may reach past.
+fn read_tar(mut reader: impl Read, length: Option<u64>) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = vec![];
+    let mut long_name: Option<String> = None;
+    let mut header = [0; 512];
+    let mut position: u64 = 0;
+    loop {
+        match reader.read_exact(&mut header) {
+            Ok(_) => {}
+            // some writers leave out the closing zero blocks
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        let size = octal(&header[124..136]);
+        let mut data = vec![];
+        let padded = size.div_ceil(512) * 512;
+        let Some(next) = (position + 512)
+            .checked_add(padded)
+            .filter(|&next| length.is_none_or(|length| next <= length))
+        else {
+            return Err(invalid("tar entry is past the end of the file"));
+        };
+        position = next;
+        if matches!(header[156], b'L' | b'x') && size > MAX_LONG_NAME {
+            return Err(invalid("tar long name is too long"));
+        }
+        match header[156] {
+            b'L' | b'x' => {
+                reader.by_ref().take(padded).read_to_end(&mut data)?;
+                data.truncate(size as usize);
+            }
+            _ => {
+                io::copy(&mut reader.by_ref().take(padded), &mut io::sink())?;
+            }
+        }
+        match header[156] {
+            b'L' => {
+                long_name = Some(text(&data));
+                continue;
+            }
+            b'x' => {
+                long_name = pax_path(&text(&data)).or(long_name);
+                continue;
+            }
+            b'g' => continue,
+            _ => {}
+        }
+        let name = long_name.take().unwrap_or_else(|| {
+            let name = text(&header[..100]);
+            match &header[257..262] == b"ustar" {
+                true if header[345] != 0 => format!("{}/{}", text(&header[345..500]), name),
+                _ => name,
+            }
+        });
+        let path = clean_path(&name);
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+        entries.push(ArchiveEntry {
+            path,
+            is_dir: header[156] == b'5' || name.ends_with('/'),
+            size,
+            modified: UNIX_EPOCH + Duration::from_secs(octal(&header[136..148])),
+        });
+    }
+    Ok(entries)
+}
+
+/// A NUL-terminated header field.
+fn text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+fn octal(bytes: &[u8]) -> u64 {
+    let digits = text(bytes);
+    u64::from_str_radix(digits.trim(), 8).unwrap_or(0)
+}
+
+/// Pax records are `<length> <key>=<value>\n`.
+fn pax_path(records: &str) -> Option<String> {
+    records.lines().find_map(|record| {
+        let (_, pair) = record.split_once(' ')?;
+        pair.strip_prefix("path=").map(|path| path.to_string())
+    })
+}
@@ -0,0 +1,37 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    io,
+    path::PathBuf,
+};
+
+use crate::config::config_dir;
+
+/// Bookmarks are stored one path per line.
+fn bookmarks_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("bookmarks"))
+}
+
+pub fn load_bookmarks() -> Vec<String> {
+    let Some(text) = bookmarks_file().and_then(|path| read_to_string(path).ok()) else {
+        return vec![];
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+pub fn save_bookmarks(bookmarks: &[String]) -> io::Result<()> {
+    let Some(path) = bookmarks_file() else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no config directory",
+        ));
+    };
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    let mut text = bookmarks.join("\n");
+    text.push('\n');
+    write(path, text)
+}
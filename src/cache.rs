@@ -0,0 +1,69 @@
+use std::{
+    collections::VecDeque,
+    fs::metadata,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{Entry, ListOptions};
+
+const CAPACITY: usize = 64;
+
+/// Listings of the directories visited last, most recent first, so going back and forth
+/// doesn't read them again. The watcher only sees the two directories on screen, so an
+/// entry is only used while its directory's mtime is what it was when it was read.
+///
+/// That mtime only moves when entries are added, removed or renamed. A file written to in
+/// place keeps the size and date it was cached with until `r` lists the directory again,
+/// which always goes past the cache; checking every entry would cost as much as the read.
+#[derive(Debug, Default)]
+pub struct ListingCache {
+    entries: VecDeque<Cached>,
+}
+
+#[derive(Debug)]
+struct Cached {
+    path: PathBuf,
+    modified: SystemTime,
+    options: ListOptions,
+    contents: Vec<Entry>,
+}
+
+impl ListingCache {
+    /// The listing of `path` made with `options` and the mtime it was made at, if it is
+    /// still current.
+    pub fn get(&mut self, path: &Path, options: &ListOptions) -> Option<(SystemTime, Vec<Entry>)> {
+        let index = self.entries.iter().position(|cached| cached.path == path)?;
+        let cached = self.entries.remove(index)?;
+        let modified = metadata(path).and_then(|m| m.modified()).ok();
+        if modified != Some(cached.modified) || cached.options != *options {
+            return None;
+        }
+        let contents = cached.contents.clone();
+        let modified = cached.modified;
+        self.entries.push_front(cached);
+        Some((modified, contents))
+    }
+
+    /// `modified` is the directory's mtime from before it was read.
+    pub fn insert(
+        &mut self,
+        path: PathBuf,
+        modified: SystemTime,
+        options: ListOptions,
+        contents: Vec<Entry>,
+    ) {
+        self.remove(&path);
+        self.entries.push_front(Cached {
+            path,
+            modified,
+            options,
+            contents,
+        });
+        self.entries.truncate(CAPACITY);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.entries.retain(|cached| cached.path != path);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::SortMode;
+
+/// A line typed after `:`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Mkdir(String),
+    Touch(String),
+    Cd(String),
+    Delete,
+    Sort(SortMode),
+    /// The mode bits for `:chmod 755`.
+    Chmod(u32),
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+    let required = |usage: &str| match argument.is_empty() {
+        true => Err(format!("Usage: {}", usage)),
+        false => Ok(argument.to_string()),
+    };
+    match name {
+        "mkdir" => required(":mkdir <name>").map(Command::Mkdir),
+        "touch" => required(":touch <name>").map(Command::Touch),
+        "cd" => required(":cd <path>").map(Command::Cd),
+        "delete" | "rm" => Ok(Command::Delete),
+        "chmod" => u32::from_str_radix(argument, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o7777)
+            .map(Command::Chmod)
+            .ok_or_else(|| "Usage: :chmod <octal mode>".to_string()),
+        "sort" => match argument {
+            "name" => Ok(Command::Sort(SortMode::Name)),
+            "size" => Ok(Command::Sort(SortMode::Size)),
+            "modified" => Ok(Command::Sort(SortMode::ModTime)),
+            _ => Err("Usage: :sort name|size|modified".to_string()),
+        },
+        "" => Err("No command given".to_string()),
+        _ => Err(format!("Unknown command: {}", name)),
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::Entry;
+
+/// How an entry relates to the one with the same name in the other pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    OnlyHere,
+    Differs,
+}
+
+impl Difference {
+    pub fn marker(self) -> char {
+        match self {
+            Difference::OnlyHere => '+',
+            Difference::Differs => '≠',
+        }
+    }
+}
+
+/// The entries missing from `other` or differing in size or modification time from their
+/// namesake there, by path. Directories are only compared by name.
+pub fn compare(entries: &[Entry], other: &[Entry]) -> HashMap<PathBuf, Difference> {
+    let other: HashMap<&OsStr, &Entry> = other
+        .iter()
+        .filter_map(|entry| Some((entry.path.file_name()?, entry)))
+        .collect();
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let difference = match other.get(entry.path.file_name()?) {
+                None => Difference::OnlyHere,
+                Some(namesake) if entry.is_dir && namesake.is_dir => return None,
+                Some(namesake)
+                    if entry.is_dir != namesake.is_dir
+                        || entry.size != namesake.size
+                        || seconds(entry.modified) != seconds(namesake.modified) =>
+                {
+                    Difference::Differs
+                }
+                Some(_) => return None,
+            };
+            Some((entry.path.clone(), difference))
+        })
+        .collect()
+}
+
+/// File systems keep times at different precisions, so only whole seconds count.
+fn seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|since| since.as_secs())
+}
@@ -0,0 +1,52 @@
+use std::{
+    fs::read_dir,
+    path::{is_separator, Path, MAIN_SEPARATOR},
+};
+
+use crate::expand_tilde;
+
+pub struct Completion {
+    /// The input with the last component completed as far as it is unambiguous.
+    pub input: String,
+    /// Every directory the last component could still become.
+    pub candidates: Vec<String>,
+}
+
+/// Completes the last component of `input` to a directory, like a shell's Tab. Relative
+/// input is completed from `base`.
+pub fn complete_dir(input: &str, base: &Path) -> Completion {
+    let (parent, prefix) = match input.rfind(is_separator) {
+        Some(index) => input.split_at(index + 1),
+        None => ("", input),
+    };
+    let dir = base.join(expand_tilde(parent));
+    let mut candidates: Vec<String> = read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        // follows symlinks, since a link to a directory can be jumped into too
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(prefix))
+        // hidden directories only once the component starts with a dot
+        .filter(|name| prefix.starts_with('.') || !name.starts_with('.'))
+        .collect();
+    candidates.sort();
+
+    let completed = match candidates.as_slice() {
+        [] => prefix.to_string(),
+        [only] => format!("{}{}", only, MAIN_SEPARATOR),
+        [first, rest @ ..] => rest.iter().fold(first.clone(), |common, name| {
+            common
+                .chars()
+                .zip(name.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        }),
+    };
+    Completion {
+        input: format!("{}{}", parent, completed),
+        candidates,
+    }
+}
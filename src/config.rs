@@ -0,0 +1,467 @@
+use std::{collections::HashMap, env, fs::read_to_string, io, path::PathBuf};
+
+use crate::{
+    dates::TimeFormat,
+    fuzzy::CaseMode,
+    home_dir,
+    keymap::{default_keymap, parse_key, Action, Key},
+    theme::{parse_color, Theme},
+    SortMode,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+/// Top level keys live in the table named "".
+pub type Tables = HashMap<String, HashMap<String, Value>>;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub left_path: String,
+    pub right_path: String,
+    pub show_hidden: bool,
+    pub sort_mode: SortMode,
+    pub builtin_matcher: bool,
+    /// How many levels below the typed path the suggestions reach.
+    pub fzf_max_depth: usize,
+    pub fzf_case: CaseMode,
+    /// Path suggestions include files, which open highlighted in their directory.
+    pub fzf_files: bool,
+    pub wrap_navigation: bool,
+    /// Entering a symlinked directory resolves it to its target.
+    pub follow_symlinks: bool,
+    /// Unix only: adds permission bits, owner and group to the listing.
+    pub show_permissions: bool,
+    pub time_format: TimeFormat,
+    /// Delete removes entries for good instead of moving them to the trash.
+    pub permanent_delete: bool,
+    /// Draws an icon before each entry, which needs a nerd font.
+    pub use_icons: bool,
+    /// Colors the preview of source files by their extension.
+    pub syntax_highlight: bool,
+    pub theme: Theme,
+    /// The built-in keys plus the `[keybindings]` from the config file.
+    pub keymap: HashMap<Key, Action>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            left_path: "~/".to_string(),
+            right_path: "~/".to_string(),
+            show_hidden: false,
+            sort_mode: SortMode::Name,
+            builtin_matcher: false,
+            fzf_max_depth: 3,
+            fzf_case: CaseMode::Smart,
+            fzf_files: false,
+            wrap_navigation: false,
+            follow_symlinks: false,
+            show_permissions: false,
+            time_format: TimeFormat::Relative,
+            permanent_delete: false,
+            use_icons: false,
+            syntax_highlight: false,
+            theme: Theme::default(),
+            keymap: default_keymap(),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/file-browser`, or `%APPDATA%\file-browser` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let variable = if cfg!(windows) {
+        "APPDATA"
+    } else {
+        "XDG_CONFIG_HOME"
+    };
+    let base = match env::var(variable) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".config"),
+    };
+    Some(base.join("file-browser"))
+}
+
+impl Config {
+    /// Reads `config.toml` from the config dir, with what couldn't be applied from it. A
+    /// missing file gives the defaults.
+    pub fn load() -> (Config, Vec<String>) {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return (Config::default(), vec![]);
+        };
+        let (config, problems) = match read_to_string(&path) {
+            Ok(text) => Config::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (Config::default(), vec![]),
+            Err(e) => (Config::default(), vec![e.to_string()]),
+        };
+        let problems = problems
+            .into_iter()
+            .map(|problem| format!("{}: {}", path.display(), problem))
+            .collect();
+        (config, problems)
+    }
+
+    /// Applies every setting it can read over the defaults, describing the ones it can't
+    /// in the second list.
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let (tables, mut problems) = parse_toml(text);
+        let mut config = Config::default();
+        let empty = HashMap::new();
+        let root = tables.get("").unwrap_or(&empty);
+
+        for (key, value) in root {
+            if let Err(e) = config.set(key, value) {
+                problems.push(e);
+            }
+        }
+
+        for (key, value) in tables.get("theme").unwrap_or(&empty) {
+            let color = match value {
+                Value::String(color) => parse_color(color),
+                _ => None,
+            };
+            match color {
+                Some(color) if config.theme.set(key, color) => {}
+                Some(_) => problems.push(format!("unknown theme color {}", key)),
+                None => problems.push(format!("theme.{} is not a color", key)),
+            }
+        }
+
+        let defaults = default_keymap();
+        for (key, value) in tables.get("keybindings").unwrap_or(&empty) {
+            let (Some(code), Value::String(target)) = (parse_key(key), value) else {
+                problems.push(format!("keybinding {} must map a key to an action", key));
+                continue;
+            };
+            // a key as the target, the older form, takes over that key's built-in action
+            let action = Action::from_name(target)
+                .or_else(|| parse_key(target).and_then(|target| defaults.get(&target).copied()));
+            let Some(action) = action else {
+                problems.push(format!("keybinding {}: unknown action \"{}\"", key, target));
+                continue;
+            };
+            config.keymap.insert(code, action);
+        }
+
+        (config, problems)
+    }
+
+    /// Applies one top level setting.
+    fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match (key, value) {
+            ("left_path", Value::String(path)) => self.left_path = path.clone(),
+            ("right_path", Value::String(path)) => self.right_path = path.clone(),
+            ("show_hidden", Value::Boolean(show)) => self.show_hidden = *show,
+            ("builtin_matcher", Value::Boolean(builtin)) => self.builtin_matcher = *builtin,
+            ("wrap_navigation", Value::Boolean(wrap)) => self.wrap_navigation = *wrap,
+            ("follow_symlinks", Value::Boolean(follow)) => self.follow_symlinks = *follow,
+            ("fzf_files", Value::Boolean(files)) => self.fzf_files = *files,
+            ("show_permissions", Value::Boolean(show)) => self.show_permissions = *show,
+            ("permanent_delete", Value::Boolean(permanent)) => self.permanent_delete = *permanent,
+            ("use_icons", Value::Boolean(icons)) => self.use_icons = *icons,
+            ("syntax_highlight", Value::Boolean(highlight)) => self.syntax_highlight = *highlight,
+            ("theme", Value::String(name)) => {
+                self.theme =
+                    Theme::builtin(name).ok_or_else(|| format!("unknown theme \"{}\"", name))?;
+            }
+            ("time_format", Value::String(format)) => {
+                self.time_format = match format.as_str() {
+                    "relative" => TimeFormat::Relative,
+                    "absolute" => TimeFormat::Absolute,
+                    _ => return Err(format!("unknown time_format \"{}\"", format)),
+                }
+            }
+            ("fzf_max_depth", Value::Integer(depth)) => {
+                self.fzf_max_depth = usize::try_from(*depth)
+                    .ok()
+                    .filter(|depth| *depth >= 1)
+                    .ok_or_else(|| format!("fzf_max_depth must be at least 1, not {}", depth))?;
+            }
+            ("fzf_case", Value::String(case)) => {
+                self.fzf_case = match case.as_str() {
+                    "smart" => CaseMode::Smart,
+                    "ignore" => CaseMode::Ignore,
+                    "respect" => CaseMode::Respect,
+                    _ => return Err(format!("unknown fzf_case \"{}\"", case)),
+                }
+            }
+            ("sort_mode", Value::String(mode)) => {
+                self.sort_mode = match mode.as_str() {
+                    "name" => SortMode::Name,
+                    "size" => SortMode::Size,
+                    "modified" => SortMode::ModTime,
+                    _ => return Err(format!("unknown sort_mode \"{}\"", mode)),
+                }
+            }
+            _ => return Err(format!("unexpected setting {} = {:?}", key, value)),
+        }
+        Ok(())
+    }
+}
+
+/// Parses the subset of TOML the config needs: `[table]` headers and `key = value` lines
+/// with string, integer and boolean values. Lines it can't read, arrays among them, are
+/// skipped and described in the second list, so one bad line doesn't lose the rest.
+pub fn parse_toml(text: &str) -> (Tables, Vec<String>) {
+    let mut tables = Tables::new();
+    let mut problems = vec![];
+    let mut table = String::new();
+    tables.insert(table.clone(), HashMap::new());
+
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_line(line) {
+            Ok(Line::Header(name)) => {
+                tables.entry(name.clone()).or_default();
+                table = name;
+            }
+            Ok(Line::Setting(key, value)) => {
+                tables.entry(table.clone()).or_default().insert(key, value);
+            }
+            Err(message) => problems.push(format!("line {}: {}", number + 1, message)),
+        }
+    }
+    (tables, problems)
+}
+
+enum Line {
+    Header(String),
+    Setting(String, Value),
+}
+
+fn parse_line(line: &str) -> Result<Line, &'static str> {
+    if let Some(header) = line.strip_prefix('[') {
+        let name = header.strip_suffix(']').ok_or("unclosed table header")?;
+        let name = unquote_key(name.trim()).ok_or("invalid table name")?;
+        return Ok(Line::Header(name));
+    }
+    let (key, value) = split_key_value(line).ok_or("expected key = value")?;
+    let key = unquote_key(key.trim()).ok_or("invalid key")?;
+    let value = parse_value(value.trim()).ok_or("invalid value")?;
+    Ok(Line::Setting(key, value))
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..index],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    // the key may itself be quoted and contain '='
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '=') => return Some((&line[..index], &line[index + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unquote_key(key: &str) -> Option<String> {
+    if key.starts_with('"') || key.starts_with('\'') {
+        return match parse_value(key)? {
+            Value::String(key) => Some(key),
+            _ => None,
+        };
+    }
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    bare.then(|| key.to_string())
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        return Some(Value::String(literal.strip_suffix('\'')?.to_string()));
+    }
+    if let Some(basic) = value.strip_prefix('"') {
+        let basic = basic.strip_suffix('"')?;
+        let mut unescaped = String::new();
+        let mut chars = basic.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unescaped.push(match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    '\\' => '\\',
+                    '"' => '"',
+                    _ => return None,
+                }),
+                '"' => return None,
+                c => unescaped.push(c),
+            }
+        }
+        return Some(Value::String(unescaped));
+    }
+    match value {
+        "true" => Some(Value::Boolean(true)),
+        "false" => Some(Value::Boolean(false)),
+        _ => value.replace('_', "").parse().ok().map(Value::Integer),
+    }
+}
+
+/// Writes `text` as a basic string that `parse_value` reads back unchanged.
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(text: &str) -> HashMap<String, Value> {
+        let (mut tables, problems) = parse_toml(text);
+        assert_eq!(problems, Vec::<String>::new());
+        tables.remove("").unwrap()
+    }
+
+    fn string(value: &str) -> Option<Value> {
+        Some(Value::String(value.to_string()))
+    }
+
+    #[test]
+    fn comments_end_values_but_not_strings() {
+        let root = root(concat!(
+            "# a whole line\n",
+            "a = \"x # y\" # a comment\n",
+            "b = 'x # y'   # another\n",
+            "c = 10#no space\n",
+        ));
+        assert_eq!(root.get("a").cloned(), string("x # y"));
+        assert_eq!(root.get("b").cloned(), string("x # y"));
+        assert_eq!(root.get("c"), Some(&Value::Integer(10)));
+    }
+
+    #[test]
+    fn escapes_are_read_in_basic_strings_only() {
+        let root = root(concat!(
+            r#"a = "say \"hi\" # \\ there\t""#,
+            "\n",
+            r"b = 'C:\temp\'",
+            "\n",
+        ));
+        assert_eq!(root.get("a").cloned(), string("say \"hi\" # \\ there\t"));
+        assert_eq!(root.get("b").cloned(), string(r"C:\temp\"));
+        assert_eq!(parse_value(r#""\q""#), None);
+        assert_eq!(parse_value(r#""a"b""#), None);
+    }
+
+    #[test]
+    fn keys_and_tables_can_be_quoted() {
+        let (tables, problems) = parse_toml(concat!(
+            "\"my key\" = true\n",
+            "'a=b' = 1\n",
+            "[\"odd table\"]\n",
+            "\"#\" = \"hash\"\n",
+        ));
+        assert!(problems.is_empty());
+        assert_eq!(tables[""]["my key"], Value::Boolean(true));
+        assert_eq!(tables[""]["a=b"], Value::Integer(1));
+        assert_eq!(tables["odd table"].get("#").cloned(), string("hash"));
+    }
+
+    #[test]
+    fn arrays_are_skipped() {
+        let (tables, problems) = parse_toml("list = [\"a\", \"#\"]\nafter = 1\n");
+        assert_eq!(problems, vec!["line 1: invalid value".to_string()]);
+        assert!(!tables[""].contains_key("list"));
+        assert_eq!(tables[""]["after"], Value::Integer(1));
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let (tables, problems) = parse_toml(concat!(
+            "no equals sign\n",
+            "[unclosed\n",
+            "[bad table!]\n",
+            "= 1\n",
+            "bad key = 1\n",
+            "a = \"unterminated\n",
+            "b =\n",
+            "c = 'é\n",
+            "\"\" = \"\n",
+            "good = \"🚀\"\n",
+        ));
+        assert_eq!(problems.len(), 9);
+        assert!(problems[1].starts_with("line 2:"));
+        assert_eq!(tables[""].len(), 1);
+        assert_eq!(tables[""].get("good").cloned(), string("🚀"));
+    }
+
+    #[test]
+    fn quoted_names_read_back_unchanged() {
+        for name in [
+            "/tmp/a b",
+            "it's a \"dir\"",
+            r"C:\Users\me\",
+            "emoji 🚀 and 名前",
+            "tab\tand\nnewline",
+            "# not a comment",
+            "",
+        ] {
+            assert_eq!(parse_value(&quote(name)), string(name));
+            let line = format!("path = {} # saved", quote(name));
+            assert_eq!(root(&line).get("path").cloned(), string(name));
+        }
+    }
+
+    #[test]
+    fn a_bad_line_leaves_the_other_settings_applied() {
+        let (config, problems) = Config::parse(concat!(
+            "left_path = \"/tmp\"\n",
+            "show_hidden = yes\n",
+            "[keybindings]\n",
+            "x = \"no_such_action\"\n",
+            "z = \"quit\"\n",
+        ));
+        assert_eq!(config.left_path, "/tmp");
+        assert!(!config.show_hidden);
+        assert_eq!(
+            config.keymap.get(&parse_key("z").unwrap()),
+            Some(&Action::Quit)
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "line 2: invalid value".to_string(),
+                "keybinding x: unknown action \"no_such_action\"".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeFormat {
+    /// "3m ago", "2 days ago", falling back to the date after a month.
+    Relative,
+    Absolute,
+}
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+pub fn format_time(time: SystemTime, now: SystemTime, format: TimeFormat) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    if format == TimeFormat::Absolute {
+        return format!(
+            "{} {:02}:{:02}",
+            format_date(seconds),
+            seconds % DAY / HOUR,
+            seconds % HOUR / MINUTE
+        );
+    }
+    // times in the future, e.g. from clock skew, count as just now
+    let age = now.duration_since(time).map_or(0, |age| age.as_secs());
+    match age {
+        age if age < MINUTE => "just now".to_string(),
+        age if age < HOUR => format!("{}m ago", age / MINUTE),
+        age if age < DAY => format!("{}h ago", age / HOUR),
+        age if age < 2 * DAY => "1 day ago".to_string(),
+        age if age < 30 * DAY => format!("{} days ago", age / DAY),
+        _ => format_date(seconds),
+    }
+}
+
+/// The UTC time of a unix timestamp as `YYYY-MM-DDThh:mm:ss`.
+pub fn format_timestamp(seconds: u64) -> String {
+    format!(
+        "{}T{:02}:{:02}:{:02}",
+        format_date(seconds),
+        seconds % DAY / HOUR,
+        seconds % HOUR / MINUTE,
+        seconds % MINUTE
+    )
+}
+
+/// The UTC date of a unix timestamp as `YYYY-MM-DD`.
+fn format_date(seconds: u64) -> String {
+    // Howard Hinnant's days-to-civil algorithm
+    let days = (seconds / DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Days from 1970-01-01 to a date, the inverse of `format_date`.
+pub fn days_since_epoch(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = (month + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
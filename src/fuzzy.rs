@@ -0,0 +1,131 @@
+use std::{
+    cmp::Reverse,
+    fs::read_dir,
+    path::{Path, PathBuf},
+};
+
+const MAX_CANDIDATES: usize = 20_000;
+
+/// What the path prompts suggest, for both fzf and the built-in matcher.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SuggestionOptions {
+    /// How many levels below the typed path the suggestions reach.
+    pub max_depth: usize,
+    pub case: CaseMode,
+    /// Files are suggested along with directories, toggled with Ctrl+F.
+    pub files: bool,
+}
+
+/// How path suggestions treat case, cycled with Ctrl+S in the path prompts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseMode {
+    /// Case sensitive only once the query has an uppercase letter, like fzf by default.
+    Smart,
+    Ignore,
+    Respect,
+}
+
+impl CaseMode {
+    pub fn next(self) -> CaseMode {
+        match self {
+            CaseMode::Smart => CaseMode::Ignore,
+            CaseMode::Ignore => CaseMode::Respect,
+            CaseMode::Respect => CaseMode::Smart,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CaseMode::Smart => "smart case",
+            CaseMode::Ignore => "ignore case",
+            CaseMode::Respect => "match case",
+        }
+    }
+
+    /// The fzf option for the mode; smart case is what fzf does without one.
+    pub fn fzf_flag(self) -> Option<&'static str> {
+        match self {
+            CaseMode::Smart => None,
+            CaseMode::Ignore => Some("-i"),
+            CaseMode::Respect => Some("+i"),
+        }
+    }
+
+    fn ignores_case(self, query: &str) -> bool {
+        match self {
+            CaseMode::Smart => !query.chars().any(char::is_uppercase),
+            CaseMode::Ignore => true,
+            CaseMode::Respect => false,
+        }
+    }
+}
+
+pub fn fuzzy_suggestions(query: &str, root: &Path, options: SuggestionOptions) -> Vec<String> {
+    let mut candidates = vec![];
+    walk_dirs(root, options.max_depth, options.files, &mut candidates);
+    let ignore_case = options.case.ignores_case(query);
+
+    let mut scored: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|path| {
+            let path = path.to_string_lossy().to_string();
+            score(query, &path, ignore_case).map(|score| (score, path))
+        })
+        .collect();
+    scored.sort_by_key(|(score, path)| (Reverse(*score), path.len()));
+    scored.into_iter().map(|(_, path)| path).collect()
+}
+
+fn walk_dirs(dir: &Path, levels_left: usize, files: bool, candidates: &mut Vec<PathBuf>) {
+    if candidates.len() >= MAX_CANDIDATES {
+        return;
+    }
+    candidates.push(dir.to_path_buf());
+    if levels_left == 0 {
+        return;
+    }
+    let Ok(entries) = read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        // like find, symlinks are not followed so link cycles can't recurse forever
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            walk_dirs(&entry.path(), levels_left - 1, files, candidates);
+        } else if files && candidates.len() < MAX_CANDIDATES {
+            candidates.push(entry.path());
+        }
+    }
+}
+
+/// Scores `candidate` if every character of `query` appears in it in order. Consecutive
+/// matches and matches at the start of a path component score higher.
+fn score(query: &str, candidate: &str, ignore_case: bool) -> Option<i64> {
+    let fold = |text: &str| -> Vec<char> {
+        match ignore_case {
+            true => text.chars().flat_map(char::to_lowercase).collect(),
+            false => text.chars().collect(),
+        }
+    };
+    let candidate = fold(candidate);
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in fold(query) {
+        let found = candidate[position..]
+            .iter()
+            .position(|c| *c == query_char)?;
+        let index = position + found;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || matches!(candidate[index - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 3;
+        }
+        score -= found.min(10) as i64;
+
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
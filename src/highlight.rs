@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+/// Just enough of a language to color its keywords, strings, comments and numbers.
+pub struct Syntax {
+    keywords: &'static [&'static str],
+    line_comment: &'static str,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const RUST: Syntax = Syntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    // a single quote is as often a lifetime as a char
+    quotes: &['"'],
+};
+
+const C_LIKE: Syntax = Syntax {
+    keywords: &[
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "do",
+        "else",
+        "enum",
+        "extends",
+        "false",
+        "for",
+        "func",
+        "function",
+        "go",
+        "if",
+        "import",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "static",
+        "struct",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typedef",
+        "var",
+        "void",
+        "while",
+    ],
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+
+const PYTHON: Syntax = Syntax {
+    keywords: &[
+        "and", "as", "async", "await", "break", "class", "continue", "def", "elif", "else",
+        "except", "False", "finally", "for", "from", "if", "import", "in", "is", "lambda", "None",
+        "not", "or", "pass", "raise", "return", "True", "try", "while", "with", "yield",
+    ],
+    line_comment: "#",
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+const SHELL: Syntax = Syntax {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "until", "while",
+    ],
+    line_comment: "#",
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+const TOML: Syntax = Syntax {
+    keywords: &["false", "true"],
+    line_comment: "#",
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+const KEYWORD: Color = Color::Magenta;
+const STRING: Color = Color::Green;
+const COMMENT: Color = Color::DarkGray;
+const NUMBER: Color = Color::Yellow;
+
+/// The syntax for the file's extension, if it is one of the few known ones.
+pub fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "rs" => Some(&RUST),
+        "c" | "h" | "cpp" | "cc" | "hpp" | "java" | "js" | "mjs" | "cjs" | "ts" | "tsx" | "go" => {
+            Some(&C_LIKE)
+        }
+        "py" => Some(&PYTHON),
+        "sh" | "bash" | "zsh" => Some(&SHELL),
+        "toml" | "ini" | "conf" => Some(&TOML),
+        _ => None,
+    }
+}
+
+/// Colors `lines` one after another, so a block comment can carry over to the next line.
+/// Plain text is left unstyled to take the color of the preview.
+pub fn highlight(lines: &[String], syntax: &Syntax) -> Vec<Line<'static>> {
+    let mut in_comment = false;
+    lines
+        .iter()
+        .map(|line| highlight_line(line, syntax, &mut in_comment))
+        .collect()
+}
+
+fn highlight_line(line: &str, syntax: &Syntax, in_comment: &mut bool) -> Line<'static> {
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let (length, color) = if *in_comment {
+            let (_, end) = syntax.block_comment.unwrap_or_default();
+            match rest.find(end) {
+                Some(index) => {
+                    *in_comment = false;
+                    (index + end.len(), Some(COMMENT))
+                }
+                None => (rest.len(), Some(COMMENT)),
+            }
+        } else if rest.starts_with(syntax.line_comment) {
+            (rest.len(), Some(COMMENT))
+        } else if let Some((start, _)) = syntax
+            .block_comment
+            .filter(|(start, _)| rest.starts_with(start))
+        {
+            *in_comment = true;
+            (start.len(), Some(COMMENT))
+        } else if syntax.quotes.contains(&c) {
+            (quoted_length(rest, c), Some(STRING))
+        } else if c.is_ascii_digit() {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.')
+                .unwrap_or(rest.len());
+            (length, Some(NUMBER))
+        } else if c.is_alphabetic() || c == '_' {
+            // a whole word at once, so digits inside it aren't taken for a number
+            let length = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let keyword = syntax.keywords.contains(&&rest[..length]);
+            (length, keyword.then_some(KEYWORD))
+        } else {
+            (c.len_utf8(), None)
+        };
+        let (taken, after) = rest.split_at(length);
+        match color {
+            Some(color) => {
+                if !plain.is_empty() {
+                    spans.push(Span::raw(std::mem::take(&mut plain)));
+                }
+                spans.push(Span::styled(taken.to_string(), Style::default().fg(color)));
+            }
+            None => plain.push_str(taken),
+        }
+        rest = after;
+    }
+    if !plain.is_empty() {
+        spans.push(Span::raw(plain));
+    }
+    Line::from(spans)
+}
+
+/// The bytes up to and including the closing `quote`, or the rest of the line when a
+/// string isn't closed on it.
+fn quoted_length(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return index + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
@@ -0,0 +1,116 @@
+use std::{
+    fs::{metadata, read_dir, remove_dir, remove_file, rename, symlink_metadata},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{delete_entry, display_name, move_path, trash::restore_from_trash};
+
+/// How many operations `u` can step back through.
+pub const HISTORY_LEN: usize = 20;
+
+/// A file operation with what is needed to reverse it. Permanent deletes aren't recorded.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// The copies that were created, each with the newest mtime in it when it was made.
+    Copied(Vec<(PathBuf, SystemTime)>),
+    /// Pairs of where each entry was and where it went.
+    Moved(Vec<(PathBuf, PathBuf)>),
+    Renamed(PathBuf, PathBuf),
+    /// Pairs of the original path and its place in the trash.
+    Trashed(Vec<(PathBuf, PathBuf)>),
+    CreatedDir(PathBuf),
+    CreatedFile(PathBuf),
+}
+
+impl Operation {
+    /// Records `copies` as they are now, so undoing them can tell if they were changed.
+    /// Walks them, so it belongs on the thread that made them.
+    pub fn copied(copies: Vec<PathBuf>) -> Operation {
+        Operation::Copied(
+            copies
+                .into_iter()
+                .map(|copy| {
+                    // a copy that can't be read counts as changed and isn't deleted
+                    let changed = last_change(&copy).unwrap_or(UNIX_EPOCH);
+                    (copy, changed)
+                })
+                .collect(),
+        )
+    }
+
+    /// Reverses the operation, refusing to overwrite anything that took its place since.
+    pub fn undo(&self) -> io::Result<()> {
+        match self {
+            // checked up front so that nothing is deleted when one of them was changed
+            Operation::Copied(copies) => {
+                for (copy, changed) in copies {
+                    if last_change(copy)? != *changed {
+                        return Err(io::Error::other(format!(
+                            "{} was changed since it was copied",
+                            copy.display()
+                        )));
+                    }
+                }
+                copies.iter().try_for_each(|(copy, _)| delete_entry(copy))
+            }
+            Operation::Moved(moves) => moves
+                .iter()
+                .try_for_each(|(from, to)| free(from).and_then(|_| move_path(to, from))),
+            Operation::Renamed(from, to) => free(from).and_then(|_| rename(to, from)),
+            Operation::Trashed(items) => items.iter().try_for_each(|(original, trashed)| {
+                free(original).and_then(|_| restore_from_trash(trashed, original))
+            }),
+            // only undone while still empty
+            Operation::CreatedDir(dir) => remove_dir(dir),
+            // only undone while still empty, so nothing written to it since is lost
+            Operation::CreatedFile(file) => match metadata(file)?.len() {
+                0 => remove_file(file),
+                _ => Err(io::Error::other(format!(
+                    "{} is no longer empty",
+                    file.display()
+                ))),
+            },
+        }
+    }
+
+    /// For the status line, e.g. "copy of notes.txt" or "move of 3 entries".
+    pub fn describe(&self) -> String {
+        let (verb, paths): (&str, Vec<&PathBuf>) = match self {
+            Operation::Copied(copies) => ("copy", copies.iter().map(|(copy, _)| copy).collect()),
+            Operation::Moved(moves) => ("move", moves.iter().map(|(from, _)| from).collect()),
+            Operation::Renamed(from, _) => ("rename", vec![from]),
+            Operation::Trashed(items) => ("trash", items.iter().map(|(path, _)| path).collect()),
+            Operation::CreatedDir(path) | Operation::CreatedFile(path) => ("creation", vec![path]),
+        };
+        match paths.as_slice() {
+            [path] => format!("{} of {}", verb, display_name(path)),
+            paths => format!("{} of {} entries", verb, paths.len()),
+        }
+    }
+}
+
+/// The newest mtime of `path` and everything under it. Writing to a file moves its mtime
+/// and adding, removing or renaming an entry moves its directory's, so this changes with
+/// any change to the tree.
+fn last_change(path: &Path) -> io::Result<SystemTime> {
+    let meta = symlink_metadata(path)?;
+    let mut latest = meta.modified()?;
+    if meta.is_dir() {
+        for entry in read_dir(path)? {
+            latest = latest.max(last_change(&entry?.path())?);
+        }
+    }
+    Ok(latest)
+}
+
+fn free(path: &Path) -> io::Result<()> {
+    match symlink_metadata(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display()),
+        )),
+        Err(_) => Ok(()),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::Entry;
+
+/// A nerd font glyph for the entry, picked by type and then by extension.
+pub fn icon_for(entry: &Entry) -> &'static str {
+    if entry.link.as_ref().is_some_and(|link| link.broken) {
+        return "\u{f127}"; // chain broken
+    }
+    if entry.is_dir {
+        return match entry.link {
+            Some(_) => "\u{f482}", // file symlink directory
+            None => "\u{f07b}",    // folder
+        };
+    }
+    if entry.link.is_some() {
+        return "\u{f481}"; // file symlink
+    }
+    let extension = entry
+        .path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rs" => "\u{e7a8}",
+        "py" => "\u{e606}",
+        "js" | "mjs" | "cjs" => "\u{e74e}",
+        "ts" | "tsx" => "\u{e628}",
+        "go" => "\u{e627}",
+        "c" | "h" => "\u{e61e}",
+        "cpp" | "cc" | "hpp" => "\u{e61d}",
+        "java" => "\u{e738}",
+        "rb" => "\u{e739}",
+        "sh" | "bash" | "zsh" | "fish" => "\u{f489}",
+        "html" | "htm" => "\u{e736}",
+        "css" | "scss" => "\u{e749}",
+        "json" | "toml" | "yaml" | "yml" | "ini" | "conf" => "\u{e615}",
+        "md" | "markdown" => "\u{e609}",
+        "txt" | "log" => "\u{f15c}",
+        "lock" => "\u{f023}",
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" => "\u{f1c5}",
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" => "\u{f1c7}",
+        "mp4" | "mkv" | "webm" | "avi" | "mov" => "\u{f1c8}",
+        "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "7z" | "rar" => "\u{f1c6}",
+        "pdf" => "\u{f1c1}",
+        _ => "\u{f15b}", // file
+    }
+}
@@ -0,0 +1,204 @@
+use std::{fs::File, io::Read, path::Path};
+
+/// Enough of the header to find the dimensions of any of the formats below.
+const HEADER_BYTES: u64 = 64 * 1024;
+/// Images bigger than this are described but not drawn, as they are sent to the terminal
+/// whole.
+const MAX_INLINE_BYTES: u64 = 16 * 1024 * 1024;
+/// Kitty takes the image in pieces of at most this much base64.
+const KITTY_CHUNK: usize = 4096;
+
+/// Whether the extension is one of the image formats the preview describes.
+pub fn is_image(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    matches!(
+        extension.as_str(),
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp"
+    )
+}
+
+/// The format and width x height of the image, read from its header.
+pub fn dimensions(path: &Path) -> Option<(&'static str, u32, u32)> {
+    let mut header = vec![];
+    File::open(path)
+        .and_then(|file| file.take(HEADER_BYTES).read_to_end(&mut header))
+        .ok()?;
+    let h = header.as_slice();
+    let be16 = |at: usize| Some(u16::from_be_bytes(h.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(h.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(h.get(at..at + 4)?.try_into().ok()?));
+    let le32 = |at: usize| Some(u32::from_le_bytes(h.get(at..at + 4)?.try_into().ok()?));
+    if h.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(("PNG", be32(16)?, be32(20)?));
+    }
+    if h.starts_with(b"GIF87a") || h.starts_with(b"GIF89a") {
+        return Some(("GIF", le16(6)?, le16(8)?));
+    }
+    if h.starts_with(b"BM") {
+        // the height is negative for images stored top down
+        return Some(("BMP", le32(18)?, (le32(22)? as i32).unsigned_abs()));
+    }
+    if h.starts_with(b"RIFF") && h.get(8..12) == Some(b"WEBP") {
+        return match h.get(12..16)? {
+            b"VP8 " => Some(("WebP", le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = le32(21)?;
+                Some(("WebP", (bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((
+                "WebP",
+                (le32(24)? & 0xff_ffff) + 1,
+                (le32(27)? & 0xff_ffff) + 1,
+            )),
+            _ => None,
+        };
+    }
+    if h.starts_with(b"\xff\xd8") {
+        // walk the segments up to the start of frame, which holds the size
+        let mut at = 2;
+        while *h.get(at)? == 0xff {
+            let marker = *h.get(at + 1)?;
+            if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                return Some(("JPEG", be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// A terminal image protocol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Graphics {
+    /// Kitty's graphics protocol, which Ghostty speaks too. Only PNG is sent, as the
+    /// terminal decodes that itself.
+    Kitty,
+    /// iTerm2's inline images, which WezTerm shows as well. The terminal decodes any of
+    /// the formats above.
+    Iterm,
+}
+
+/// The protocol the terminal is known to speak, going by what it sets in the environment.
+/// Inside tmux the escapes wouldn't reach the terminal, so there is none.
+pub fn detect_graphics() -> Option<Graphics> {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    if !var("TMUX").is_empty() {
+        return None;
+    }
+    match var("TERM_PROGRAM").as_str() {
+        "iTerm.app" | "WezTerm" => return Some(Graphics::Iterm),
+        "ghostty" => return Some(Graphics::Kitty),
+        _ => {}
+    }
+    if var("TERM") == "xterm-kitty" || !var("KITTY_WINDOW_ID").is_empty() {
+        return Some(Graphics::Kitty);
+    }
+    None
+}
+
+/// The escape sequence that draws the image at `path` from the cursor on, scaled to fit
+/// `columns` x `rows` cells with its aspect kept, or None when it can't be drawn.
+pub fn inline_image(graphics: Graphics, path: &Path, columns: u16, rows: u16) -> Option<String> {
+    let (format, width, height) = dimensions(path)?;
+    if graphics == Graphics::Kitty && format != "PNG" {
+        return None;
+    }
+    let mut bytes = vec![];
+    File::open(path)
+        .and_then(|file| file.take(MAX_INLINE_BYTES + 1).read_to_end(&mut bytes))
+        .ok()?;
+    if bytes.len() as u64 > MAX_INLINE_BYTES {
+        return None;
+    }
+    let (columns, rows) = fit_cells(width, height, columns, rows)?;
+    let data = base64(&bytes);
+    Some(match graphics {
+        Graphics::Kitty => {
+            let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+            let mut escape = String::new();
+            for (index, chunk) in chunks.iter().enumerate() {
+                let more = (index + 1 < chunks.len()) as u8;
+                // q=2 keeps the terminal from answering on stdin, C=1 leaves the cursor be
+                let control = match index {
+                    0 => format!("a=T,f=100,q=2,C=1,c={},r={},m={}", columns, rows, more),
+                    _ => format!("m={}", more),
+                };
+                let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+                escape.push_str(&format!("\x1b_G{};{}\x1b\\", control, chunk));
+            }
+            escape
+        }
+        Graphics::Iterm => format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            bytes.len(),
+            columns,
+            rows,
+            data
+        ),
+    })
+}
+
+/// Removes the images drawn with Kitty's protocol. iTerm2's are painted over instead.
+pub const KITTY_DELETE: &str = "\x1b_Ga=d,q=2\x1b\\";
+
+/// The cells a `width` x `height` pixel image covers when scaled to fit `columns` x
+/// `rows`, taking a cell to be twice as tall as it is wide.
+fn fit_cells(width: u32, height: u32, columns: u16, rows: u16) -> Option<(u16, u16)> {
+    if width == 0 || height == 0 || columns == 0 || rows == 0 {
+        return None;
+    }
+    let (width, height) = (width as u64, height as u64);
+    let rows_at_full_width = columns as u64 * height / width / 2;
+    if rows_at_full_width <= rows as u64 {
+        return Some((columns, rows_at_full_width.max(1) as u16));
+    }
+    let columns_at_full_height = rows as u64 * 2 * width / height;
+    Some((columns_at_full_height.max(1) as u16, rows))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | (byte as u32) << (16 - 8 * index)
+            });
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_pads_the_last_group() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn images_keep_their_aspect_in_cells() {
+        // a square image is half as many rows as columns
+        assert_eq!(fit_cells(100, 100, 40, 40), Some((40, 20)));
+        // a tall one is limited by the rows
+        assert_eq!(fit_cells(100, 400, 40, 10), Some((5, 10)));
+        assert_eq!(fit_cells(1000, 1, 40, 10), Some((40, 1)));
+        assert_eq!(fit_cells(0, 10, 40, 10), None);
+    }
+}
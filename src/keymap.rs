@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A key with the Ctrl and Alt modifiers held along with it. Shift is already part of
+/// the character, and some terminals report it while others don't, so it is left out.
+pub type Key = (KeyCode, KeyModifiers);
+
+pub fn key_of(event: KeyEvent) -> Key {
+    (
+        event.code,
+        event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT),
+    )
+}
+
+/// Everything a key can do in the pane view. `[keybindings]` in the config maps keys to
+/// these by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    /// Waits for a second key: `g` goes to the first entry, `p` opens the jump prompt and
+    /// `/` goes to the filesystem root.
+    Goto,
+    First,
+    Last,
+    Jump,
+    EditLeft,
+    EditRight,
+    SwitchPane,
+    NavDown,
+    NavUp,
+    /// Enters directories and archives.
+    Enter,
+    /// Like `Enter`, but edits files too.
+    Open,
+    OpenDefault,
+    Edit,
+    Shell,
+    /// Enter with `--pick`: quits with the active pane's directory.
+    Pick,
+    Parent,
+    Home,
+    Root,
+    Delete,
+    Mark,
+    PageDown,
+    PageUp,
+    PreviewDown,
+    PreviewUp,
+    Copy,
+    Move,
+    Rename,
+    CycleSort,
+    ToggleHidden,
+    ToggleFollowSymlinks,
+    NewDir,
+    NewFile,
+    Command,
+    Search,
+    Filter,
+    ClearFilter,
+    Help,
+    Swap,
+    Mirror,
+    Compare,
+    DirSize,
+    ShrinkLeft,
+    GrowLeft,
+    EditExtensions,
+    ToggleExtensions,
+    Refresh,
+    CopyPath,
+    CopyName,
+    Undo,
+    Bookmark,
+    Bookmarks,
+}
+
+/// The names `[keybindings]` uses for the actions.
+const NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("goto", Action::Goto),
+    ("first", Action::First),
+    ("last", Action::Last),
+    ("jump", Action::Jump),
+    ("edit_left", Action::EditLeft),
+    ("edit_right", Action::EditRight),
+    ("switch_pane", Action::SwitchPane),
+    ("nav_down", Action::NavDown),
+    ("nav_up", Action::NavUp),
+    ("enter", Action::Enter),
+    ("open", Action::Open),
+    ("open_default", Action::OpenDefault),
+    ("edit", Action::Edit),
+    ("shell", Action::Shell),
+    ("pick", Action::Pick),
+    ("parent", Action::Parent),
+    ("home", Action::Home),
+    ("root", Action::Root),
+    ("delete", Action::Delete),
+    ("mark", Action::Mark),
+    ("page_down", Action::PageDown),
+    ("page_up", Action::PageUp),
+    ("preview_down", Action::PreviewDown),
+    ("preview_up", Action::PreviewUp),
+    ("copy", Action::Copy),
+    ("move", Action::Move),
+    ("rename", Action::Rename),
+    ("cycle_sort", Action::CycleSort),
+    ("toggle_hidden", Action::ToggleHidden),
+    ("toggle_follow_symlinks", Action::ToggleFollowSymlinks),
+    ("new_dir", Action::NewDir),
+    ("new_file", Action::NewFile),
+    ("command", Action::Command),
+    ("search", Action::Search),
+    ("filter", Action::Filter),
+    ("clear_filter", Action::ClearFilter),
+    ("help", Action::Help),
+    ("swap", Action::Swap),
+    ("mirror", Action::Mirror),
+    ("compare", Action::Compare),
+    ("dir_size", Action::DirSize),
+    ("shrink_left", Action::ShrinkLeft),
+    ("grow_left", Action::GrowLeft),
+    ("edit_extensions", Action::EditExtensions),
+    ("toggle_extensions", Action::ToggleExtensions),
+    ("refresh", Action::Refresh),
+    ("copy_path", Action::CopyPath),
+    ("copy_name", Action::CopyName),
+    ("undo", Action::Undo),
+    ("bookmark", Action::Bookmark),
+    ("bookmarks", Action::Bookmarks),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        NAMES
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| *action)
+    }
+}
+
+/// The built-in keys, which `[keybindings]` entries are added on top of.
+pub fn default_keymap() -> HashMap<Key, Action> {
+    let chars = [
+        ('q', Action::Quit),
+        ('g', Action::Goto),
+        ('G', Action::Last),
+        ('H', Action::EditLeft),
+        ('L', Action::EditRight),
+        ('j', Action::NavDown),
+        ('k', Action::NavUp),
+        ('l', Action::Enter),
+        ('o', Action::OpenDefault),
+        ('e', Action::Edit),
+        ('!', Action::Shell),
+        ('h', Action::Parent),
+        ('~', Action::Home),
+        ('d', Action::Delete),
+        (' ', Action::Mark),
+        ('c', Action::Copy),
+        ('m', Action::Move),
+        ('R', Action::Rename),
+        ('s', Action::CycleSort),
+        ('.', Action::ToggleHidden),
+        ('F', Action::ToggleFollowSymlinks),
+        ('n', Action::NewDir),
+        ('N', Action::NewFile),
+        (':', Action::Command),
+        ('f', Action::Search),
+        ('/', Action::Filter),
+        ('?', Action::Help),
+        ('x', Action::Swap),
+        ('=', Action::Mirror),
+        ('C', Action::Compare),
+        ('S', Action::DirSize),
+        ('<', Action::ShrinkLeft),
+        ('>', Action::GrowLeft),
+        ('E', Action::EditExtensions),
+        ('t', Action::ToggleExtensions),
+        ('r', Action::Refresh),
+        ('y', Action::CopyPath),
+        ('Y', Action::CopyName),
+        ('u', Action::Undo),
+        ('b', Action::Bookmark),
+        ('\'', Action::Bookmarks),
+    ];
+    let keys = [
+        (KeyCode::Tab, Action::SwitchPane),
+        (KeyCode::Down, Action::NavDown),
+        (KeyCode::Up, Action::NavUp),
+        (KeyCode::Enter, Action::Open),
+        (KeyCode::Backspace, Action::Parent),
+        (KeyCode::Delete, Action::Delete),
+        (KeyCode::Esc, Action::ClearFilter),
+        (KeyCode::PageDown, Action::PageDown),
+        (KeyCode::PageUp, Action::PageUp),
+    ];
+    let control = [
+        ('c', Action::Quit),
+        ('d', Action::PageDown),
+        ('u', Action::PageUp),
+        ('f', Action::PageDown),
+        ('b', Action::PageUp),
+        ('e', Action::PreviewDown),
+        ('y', Action::PreviewUp),
+    ];
+    chars
+        .into_iter()
+        .map(|(c, action)| ((KeyCode::Char(c), KeyModifiers::NONE), action))
+        .chain(keys.map(|(code, action)| ((code, KeyModifiers::NONE), action)))
+        .chain(control.map(|(c, action)| ((KeyCode::Char(c), KeyModifiers::CONTROL), action)))
+        .collect()
+}
+
+/// A single character, or a key name like `Tab`, `Enter` or `Space`, ignoring case. Either
+/// can follow `ctrl-` or `alt-`, as in `ctrl-d`.
+pub fn parse_key(name: &str) -> Option<Key> {
+    let lower = name.to_lowercase();
+    for (prefix, modifier) in [
+        ("ctrl-", KeyModifiers::CONTROL),
+        ("alt-", KeyModifiers::ALT),
+    ] {
+        if lower.starts_with(prefix) && name.len() > prefix.len() {
+            let (code, modifiers) = parse_key(&name[prefix.len()..])?;
+            // terminals send Ctrl with the lowercase letter
+            let code = match code {
+                KeyCode::Char(c) if modifier == KeyModifiers::CONTROL => {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                }
+                code => code,
+            };
+            return Some((code, modifiers | modifier));
+        }
+    }
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some((KeyCode::Char(c), KeyModifiers::NONE));
+    }
+    let key = match name.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => return None,
+    };
+    Some((key, KeyModifiers::NONE))
+}
+
+/// The rows of the `?` overlay, in order. Actions sharing a row have their keys split by
+/// ` / `, matching the description.
+const HELP: &[(&[Action], &str)] = &[
+    (&[Action::Quit], "quit"),
+    (
+        &[Action::EditLeft, Action::EditRight],
+        "edit the left / right path",
+    ),
+    (&[Action::SwitchPane], "switch pane"),
+    (&[Action::NavDown], "next entry"),
+    (&[Action::NavUp], "previous entry"),
+    (&[Action::First, Action::Last], "first / last entry"),
+    (&[Action::PageDown], "page down"),
+    (&[Action::PageUp], "page up"),
+    (
+        &[Action::PreviewDown, Action::PreviewUp],
+        "scroll the preview down / up",
+    ),
+    (&[Action::Jump], "jump to a path, Tab completes directories"),
+    (&[Action::Enter], "enter directory or archive"),
+    (
+        &[Action::Open],
+        "enter directory or archive, or edit a file",
+    ),
+    (&[Action::OpenDefault], "open with the default application"),
+    (&[Action::Edit], "edit in $EDITOR, marked files together"),
+    (&[Action::Shell], "open $SHELL in the current directory"),
+    (&[Action::Parent], "parent directory"),
+    (
+        &[Action::Home, Action::Root],
+        "home directory / filesystem root",
+    ),
+    (&[Action::Delete], "move to the trash"),
+    (&[Action::Mark], "mark entry for copy, move and delete"),
+    (&[Action::Copy], "copy to the other pane, Esc cancels"),
+    (&[Action::Move], "move to the other pane"),
+    (&[Action::Rename], "rename"),
+    (&[Action::CycleSort], "cycle sort mode"),
+    (&[Action::ToggleHidden], "toggle hidden files"),
+    (
+        &[Action::ToggleFollowSymlinks],
+        "toggle following symlinked directories",
+    ),
+    (&[Action::NewDir], "new directory"),
+    (&[Action::NewFile], "new empty file"),
+    (
+        &[Action::Command],
+        "run mkdir, touch, cd, chmod, delete or sort",
+    ),
+    (&[Action::Filter], "filter the current directory"),
+    (&[Action::ClearFilter], "clear the filter"),
+    (
+        &[Action::EditExtensions, Action::ToggleExtensions],
+        "set / toggle the file extensions shown",
+    ),
+    (
+        &[Action::Search],
+        "search names below the current directory",
+    ),
+    (&[Action::Bookmark], "bookmark the current directory"),
+    (&[Action::Bookmarks], "jump to a bookmark"),
+    (&[Action::Swap], "swap the panes"),
+    (
+        &[Action::Compare],
+        "compare the panes: + only here, ≠ differs",
+    ),
+    (
+        &[Action::ShrinkLeft, Action::GrowLeft],
+        "move the divider left / right",
+    ),
+    (&[Action::Mirror], "open this directory in the other pane"),
+    (&[Action::DirSize], "calculate the size of a directory"),
+    (&[Action::Refresh], "reload both panes from disk"),
+    (&[Action::Undo], "undo the last file operation"),
+    (
+        &[Action::CopyPath, Action::CopyName],
+        "copy the path / name to the clipboard",
+    ),
+    (&[Action::Help], "toggle this help"),
+];
+
+/// The keys and description of each `?` overlay row whose actions have any keys in
+/// `keymap`, with the `goto` sequences shown as two keys.
+pub fn help_rows(keymap: &HashMap<Key, Action>) -> Vec<(String, &'static str)> {
+    let goto_keys = keys_for(keymap, Action::Goto);
+    HELP.iter()
+        .filter_map(|(actions, description)| {
+            let keys: Vec<Vec<String>> = actions
+                .iter()
+                .map(|&action| {
+                    let second = match action {
+                        Action::First => Some('g'),
+                        Action::Jump => Some('p'),
+                        Action::Root => Some('/'),
+                        _ => None,
+                    };
+                    let sequences = second.into_iter().flat_map(|second| {
+                        goto_keys
+                            .iter()
+                            .map(move |goto| format!("{}{}", goto, second))
+                    });
+                    sequences.chain(keys_for(keymap, action)).collect()
+                })
+                .collect();
+            if keys.iter().all(|keys| keys.is_empty()) {
+                return None;
+            }
+            let labels: Vec<String> = keys
+                .iter()
+                .map(|keys| match keys.is_empty() {
+                    true => "-".to_string(),
+                    false => keys.join(", "),
+                })
+                .collect();
+            Some((labels.join(" / "), *description))
+        })
+        .collect()
+}
+
+/// The names of the keys bound to `action`: plain characters first, then named keys, then
+/// those with modifiers.
+fn keys_for(keymap: &HashMap<Key, Action>, action: Action) -> Vec<String> {
+    let mut keys: Vec<&Key> = keymap
+        .iter()
+        .filter(|(_, bound)| **bound == action)
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort_by_key(|(code, modifiers)| {
+        let named = !matches!(code, KeyCode::Char(c) if *c != ' ');
+        (modifiers.bits(), named, key_name(&(*code, *modifiers)))
+    });
+    keys.into_iter().map(key_name).collect()
+}
+
+/// The way the help shows a key, as in `Ctrl-d`, `Space` or `G`.
+fn key_name((code, modifiers): &Key) -> String {
+    let mut name = String::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        name.push_str("Ctrl-");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        name.push_str("Alt-");
+    }
+    match code {
+        KeyCode::Char(' ') => name.push_str("Space"),
+        KeyCode::Char(c) => name.push(*c),
+        KeyCode::F(n) => name.push_str(&format!("F{}", n)),
+        code => name.push_str(&format!("{:?}", code)),
+    }
+    name
+}
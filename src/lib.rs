@@ -0,0 +1,3630 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs::{
+        copy, create_dir, metadata, read_dir, read_link, read_to_string, remove_dir_all,
+        remove_file, rename, symlink_metadata, File,
+    },
+    io::{self, Read, Stderr, Write},
+    path::{is_separator, Path, PathBuf, MAIN_SEPARATOR},
+    process::Stdio,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::{
+    cursor::{MoveTo, Show},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute, queue,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    prelude::{Backend, Constraint, CrosstermBackend, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal,
+};
+
+use tokio::{process::Command, sync::mpsc, task::JoinHandle};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    archive::{is_archive, read_archive, split_archive_path},
+    bookmarks::{load_bookmarks, save_bookmarks},
+    cache::ListingCache,
+    command::parse_command,
+    compare::compare,
+    completion::complete_dir,
+    dates::{format_time, TimeFormat},
+    fuzzy::fuzzy_suggestions,
+    history::{Operation, HISTORY_LEN},
+    icons::icon_for,
+    image::{inline_image, Graphics, KITTY_DELETE},
+    keymap::{help_rows, key_of, Key},
+    permissions::{mode_string, permissions_of, set_mode, Owners},
+    search::search_names,
+    session::Session,
+    theme::Theme,
+    transfer::{copy_symlink, Copier, Progress},
+    trash::move_to_trash,
+    usage::dir_size,
+    watch::Watcher,
+};
+
+pub use crate::{
+    config::Config,
+    fuzzy::{CaseMode, SuggestionOptions},
+    keymap::Action,
+    permissions::Permissions,
+};
+
+mod archive;
+mod bookmarks;
+mod cache;
+mod command;
+mod compare;
+mod completion;
+mod config;
+mod dates;
+mod fuzzy;
+mod highlight;
+mod history;
+mod icons;
+mod image;
+mod keymap;
+mod permissions;
+mod search;
+mod session;
+mod theme;
+mod transfer;
+mod trash;
+mod usage;
+mod watch;
+
+/// What the binary takes from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// These take the place of the saved session and the config.
+    pub left_path: Option<String>,
+    pub right_path: Option<String>,
+    /// Use the built-in matcher even when the config asks for fzf.
+    pub builtin_matcher: bool,
+    /// Enter picks the active pane's directory, which `start` then returns.
+    pub pick: bool,
+}
+
+/// Runs the browser in the terminal until it quits, then saves the session. Returns the
+/// directory picked with `Options::pick`, if any.
+pub async fn start(options: Options) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let (mut config, config_problems) = Config::load();
+    // the rest of the config still applies, so the first problem stands for all of them
+    let config_error = match config_problems.as_slice() {
+        [] => None,
+        [problem] => Some(format!("Ignored in config: {}", problem)),
+        [problem, rest @ ..] => Some(format!(
+            "Ignored in config: {} (and {} more)",
+            problem,
+            rest.len()
+        )),
+    };
+    config.builtin_matcher |= options.builtin_matcher;
+    let session = Session::load();
+    // a saved directory that has since disappeared falls back to the configured one
+    let saved_path = |path: Option<&String>, default: &str| match path {
+        Some(path) if expand_tilde(path).is_dir() => path.clone(),
+        _ => default.to_string(),
+    };
+    let left_path = options
+        .left_path
+        .unwrap_or_else(|| saved_path(session.as_ref().map(|s| &s.left_path), &config.left_path));
+    let right_path = options
+        .right_path
+        .unwrap_or_else(|| saved_path(session.as_ref().map(|s| &s.right_path), &config.right_path));
+
+    install_panic_hook();
+    let mut terminal = setup_terminal()?;
+    let mut state = State::new(config, &left_path, &right_path);
+    state.active = session.map_or(Side::Left, |s| s.active);
+    state.status_message = config_error;
+    state.pick = options.pick;
+    state.load_pane(Side::Left, state.left.path.clone());
+    state.load_pane(Side::Right, state.right.path.clone());
+    let result = run(&mut terminal, &mut state).await;
+    // the runtime waits for blocking tasks on shutdown, and the partial copy gets removed
+    if let Some(job) = &state.copy_job {
+        job.cancel.store(true, atomic::Ordering::Relaxed);
+    }
+    restore_terminal(&mut terminal)?;
+    let session = Session {
+        left_path: state.left.path,
+        right_path: state.right.path,
+        active: state.active,
+    };
+    if let Err(e) = session.save() {
+        eprintln!("Failed to save session: {}", e);
+    }
+    result.map(|()| state.picked)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Field {
+    LeftPath,
+    RightPath,
+    Rename,
+    NewDir,
+    NewFile,
+    Extensions,
+    Filter,
+    Search,
+    Command,
+    Jump,
+}
+
+impl Field {
+    fn is_path(self) -> bool {
+        self.side().is_some()
+    }
+
+    /// The pane a path field edits.
+    fn side(self) -> Option<Side> {
+        match self {
+            Field::LeftPath => Some(Side::Left),
+            Field::RightPath => Some(Side::Right),
+            _ => None,
+        }
+    }
+
+    fn prompt(self) -> &'static str {
+        match self {
+            Field::LeftPath => "Left path",
+            Field::RightPath => "Right path",
+            Field::Rename => "Rename",
+            Field::NewDir => "New directory",
+            Field::NewFile => "New file",
+            Field::Extensions => "Extensions (comma separated)",
+            Field::Filter => "Filter",
+            Field::Search => "Search names",
+            Field::Command => ":",
+            Field::Jump => "Jump to",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Normal,
+    Edit(Field),
+    Confirm(Confirmation),
+    Help,
+    Bookmarks,
+    /// Browsing the results of a recursive search.
+    Search,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Confirmation {
+    Delete,
+    /// Asked when `q` is pressed while background work hasn't finished.
+    Quit,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SortMode {
+    Name,
+    Size,
+    ModTime,
+}
+
+impl SortMode {
+    fn next(self) -> SortMode {
+        match self {
+            SortMode::Name => SortMode::Size,
+            SortMode::Size => SortMode::ModTime,
+            SortMode::ModTime => SortMode::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Size => "size",
+            SortMode::ModTime => "modified",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOptions {
+    pub sort_mode: SortMode,
+    pub show_hidden: bool,
+    /// Only files with one of these lowercase extensions are listed, when set.
+    pub extensions: Option<Vec<String>>,
+}
+
+impl ListOptions {
+    /// Directories always pass so the filter never gets in the way of navigating.
+    fn shows_type(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(extensions) = &self.extensions else {
+            return true;
+        };
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        is_dir || extension.is_some_and(|extension| extensions.contains(&extension))
+    }
+}
+
+/// A pane's borders, header, footer and one row of entries, and its borders, markers and
+/// icon with a few characters of name.
+const MIN_PANE_HEIGHT: u16 = 6;
+const MIN_PANE_WIDTH: u16 = 16;
+/// Below this the panes don't fit next to each other within the margins, status line and
+/// status bar.
+const MIN_WIDTH: u16 = 2 * MIN_PANE_WIDTH + 4;
+const MIN_HEIGHT: u16 = MIN_PANE_HEIGHT + 6;
+
+const SIZE_WIDTH: usize = 7;
+const MIN_NAME_WIDTH: usize = 12;
+const PREVIEW_MAX_BYTES: u64 = 64 * 1024;
+const PREVIEW_MAX_LINES: usize = 200;
+
+const FZF_DEBOUNCE: Duration = Duration::from_millis(100);
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+/// Bounds for `<` and `>` so neither pane gets squeezed away.
+const SPLIT_RANGE: (u16, u16) = (20, 80);
+const SPLIT_STEP: u16 = 5;
+const READ_ONLY: &str = "Archives are read-only";
+/// The pane path for the list of drives on Windows. No real path contains the colon.
+const DRIVES: &str = "Drives:";
+
+/// Suggestions for a pane, tagged with the `fzf_generation` of the query that produced them.
+type FzfSender = mpsc::UnboundedSender<(Side, u64, Result<Vec<String>, String>)>;
+type FzfReceiver = mpsc::UnboundedReceiver<(Side, u64, Result<Vec<String>, String>)>;
+
+#[derive(Debug)]
+pub struct Listing {
+    generation: u64,
+    path: PathBuf,
+    select: Option<PathBuf>,
+    options: ListOptions,
+    /// The directory's mtime from before it was read; None when it came from the cache.
+    modified: Option<SystemTime>,
+    result: io::Result<Vec<Entry>>,
+}
+
+type ListingSender = mpsc::UnboundedSender<Listing>;
+
+#[derive(Debug, Clone)]
+struct Search {
+    root: PathBuf,
+    query: String,
+    generation: u64,
+    /// None until the walk finishes.
+    results: Option<Vec<PathBuf>>,
+    list_state: ListState,
+}
+
+/// A directory size being worked out for `S`, dropped when its pane leaves `dir`.
+#[derive(Debug, Clone)]
+struct SizeJob {
+    side: Side,
+    dir: String,
+    path: PathBuf,
+    cancel: Arc<AtomicBool>,
+}
+
+/// A copy of `c` running in the background until it sends `CopyUpdate::Finished`.
+#[derive(Debug, Clone)]
+struct CopyJob {
+    destination: Side,
+    entries: usize,
+    progress: Progress,
+    started: Instant,
+    cancel: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone)]
+enum CopyUpdate {
+    Progress(Progress),
+    /// The status message and the copies that were made.
+    Finished(String, Operation),
+}
+
+#[derive(Debug, Clone)]
+enum PreviewContent {
+    Text(Vec<String>),
+    Code(Vec<Line<'static>>),
+    /// What is known about an image without drawing it.
+    Image(String),
+    Binary,
+    Unreadable(String),
+}
+
+#[derive(Debug, Clone)]
+struct Preview {
+    path: PathBuf,
+    content: PreviewContent,
+}
+
+impl Preview {
+    fn line_count(&self) -> usize {
+        match &self.content {
+            PreviewContent::Text(lines) => lines.len(),
+            PreviewContent::Code(lines) => lines.len(),
+            PreviewContent::Binary | PreviewContent::Unreadable(_) | PreviewContent::Image(_) => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: SystemTime,
+    /// Set for symlinks, which are listed as what they point to.
+    pub link: Option<Link>,
+    pub permissions: Option<Permissions>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub target: PathBuf,
+    /// The target doesn't exist, so the entry only has the link's own metadata.
+    pub broken: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pane {
+    /// The directory as navigated to. Entering a symlinked directory keeps the link's
+    /// path, so that going up returns to where it was entered, unless `follow_symlinks`
+    /// has it resolved to the target.
+    path: String,
+    contents: Option<Vec<Entry>>,
+    list_state: ListState,
+    /// Entries marked with Space for the next copy, move or delete.
+    marked: HashSet<PathBuf>,
+    /// Hides entries whose names don't contain it, ignoring case. `contents` stays whole.
+    filter: String,
+    /// Where the list was last drawn, for mapping mouse clicks to entries.
+    area: Rect,
+    /// The latest listing request for this pane, so older ones aren't shown.
+    generation: u64,
+    loading: bool,
+    /// The archive file when `path` is a directory inside one, which is read-only.
+    archive: Option<PathBuf>,
+    /// Sizes of subdirectories worked out with `S`, shown instead of `<DIR>`.
+    dir_sizes: HashMap<PathBuf, u64>,
+    /// The directory's mtime from before it was last read, even if the read failed, which
+    /// the watcher compares against; None inside archives and when there was no mtime.
+    modified: Option<SystemTime>,
+    /// What the path prompt for this pane last suggested.
+    suggestions: Suggestions,
+}
+
+/// Kept per pane so that one path prompt never shows the other's results.
+#[derive(Debug, Clone, Default)]
+struct Suggestions {
+    items: Vec<String>,
+    list_state: ListState,
+}
+
+impl Pane {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// A listing was requested and hasn't arrived yet.
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    /// What the path prompt for this pane suggests for its input.
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions.items
+    }
+
+    /// The directory on disk, which inside an archive is the one holding it.
+    fn real_dir(&self) -> Option<PathBuf> {
+        match &self.archive {
+            Some(archive) => archive.parent().map(Path::to_path_buf),
+            None => Some(PathBuf::from(&self.path)),
+        }
+    }
+
+    fn new(path: &str) -> Pane {
+        Pane {
+            path: expand_tilde(path).to_string_lossy().to_string(),
+            contents: None,
+            list_state: ListState::default(),
+            marked: HashSet::new(),
+            filter: String::new(),
+            area: Rect::default(),
+            generation: 0,
+            loading: false,
+            archive: None,
+            dir_sizes: HashMap::new(),
+            modified: None,
+            suggestions: Suggestions::default(),
+        }
+    }
+
+    /// Shows a finished listing of `path`, highlighting `select` if it is in it.
+    fn show(&mut self, path: PathBuf, contents: Vec<Entry>, select: Option<&Path>) {
+        // List moves the offset to keep the selection visible when drawn, but a stale
+        // offset from the previous directory would start the new one scrolled down
+        self.list_state =
+            ListState::default().with_selected(if contents.is_empty() { None } else { Some(0) });
+        self.contents = Some(contents);
+        self.marked.clear();
+        self.filter.clear();
+        self.dir_sizes.clear();
+        self.archive = split_archive_path(&path).map(|(archive, _)| archive);
+        self.path = path.to_string_lossy().to_string();
+        if let Some(select) = select {
+            self.select_path(select);
+        }
+    }
+
+    /// Shows new contents of the same directory, keeping the highlighted entry and the
+    /// marks that still exist.
+    fn update(&mut self, contents: Vec<Entry>) {
+        let selected_path = self.selected_path();
+        self.marked
+            .retain(|path| contents.iter().any(|entry| &entry.path == path));
+        self.contents = Some(contents);
+        let selected = match self.len() {
+            0 => None,
+            len => Some(self.list_state.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.list_state.select(selected);
+        if let Some(path) = selected_path {
+            self.select_path(&path);
+        }
+    }
+
+    fn resort(&mut self, sort_mode: SortMode) {
+        let selected = self.selected_path();
+        if let Some(contents) = self.contents.as_mut() {
+            sort_contents(contents, sort_mode);
+        }
+        if let Some(selected) = selected {
+            self.select_path(&selected);
+        }
+    }
+
+    fn select_path(&mut self, path: &Path) {
+        let index = self.visible().iter().position(|e| e.path == path);
+        if index.is_some() {
+            self.list_state.select(index);
+        }
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        let index = self.list_state.selected()?;
+        self.visible().get(index).copied()
+    }
+
+    /// The entries that pass the filter, which are the ones the list shows and indexes.
+    pub fn visible(&self) -> Vec<&Entry> {
+        let filter = self.filter.to_lowercase();
+        self.contents
+            .iter()
+            .flatten()
+            .filter(|entry| display_name(&entry.path).to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        let selected = (self.len() > 0).then_some(0);
+        self.list_state.select(selected);
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.selected_entry().map(|e| e.path.clone())
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(path) = self.selected_path() {
+            if !self.marked.remove(&path) {
+                self.marked.insert(path);
+            }
+        }
+    }
+
+    /// The marked entries in listing order, or the highlighted one when nothing is marked.
+    fn targets(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() {
+            return self.selected_path().into_iter().collect();
+        }
+        self.contents
+            .iter()
+            .flatten()
+            .filter(|entry| self.marked.contains(&entry.path))
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    fn contains(&self, column: u16, row: u16) -> bool {
+        let area = self.area;
+        (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+    }
+
+    /// The index of the entry drawn on `row`, skipping the list border.
+    fn index_at(&self, row: u16) -> Option<usize> {
+        let top = self.area.top() + 1;
+        if row < top || row + 1 >= self.area.bottom() {
+            return None;
+        }
+        let index = self.list_state.offset() + (row - top) as usize;
+        (index < self.len()).then_some(index)
+    }
+
+    fn len(&self) -> usize {
+        self.visible().len()
+    }
+
+    /// Directories, and archives when we aren't in one already, are listed on Enter.
+    fn can_enter(&self, entry: &Entry) -> bool {
+        entry.is_dir || (self.archive.is_none() && is_archive(&entry.path))
+    }
+
+    /// Moves down one entry, wrapping around to the first if `wrap` is set.
+    fn select_next(&mut self, wrap: bool) {
+        let len = self.len();
+        if wrap {
+            return select_next_wrapping(&mut self.list_state, len);
+        }
+        if len == 0 {
+            return;
+        }
+        let next = match self.list_state.selected() {
+            Some(v) => (v + 1).min(len - 1),
+            None => 0,
+        };
+        self.list_state.select(Some(next));
+    }
+
+    fn select_previous(&mut self, wrap: bool) {
+        let len = self.len();
+        if wrap {
+            return select_previous_wrapping(&mut self.list_state, len);
+        }
+        if len == 0 {
+            return;
+        }
+        let previous = match self.list_state.selected() {
+            Some(v) => v.saturating_sub(1),
+            None => 0,
+        };
+        self.list_state.select(Some(previous));
+    }
+
+    fn select_first(&mut self) {
+        let first = (self.len() > 0).then_some(0);
+        self.list_state.select(first);
+    }
+
+    fn select_last(&mut self) {
+        let last = self.len().checked_sub(1);
+        self.list_state.select(last);
+    }
+
+    /// Moves the selection by `rows`, stopping at either end.
+    fn move_selection(&mut self, rows: isize) {
+        let Some(last) = self.len().checked_sub(1) else {
+            return;
+        };
+        let selected = self.list_state.selected().unwrap_or(0);
+        let target = selected.saturating_add_signed(rows).min(last);
+        self.list_state.select(Some(target));
+    }
+}
+
+#[derive(Debug)]
+pub struct State {
+    mode: Mode,
+    left: Pane,
+    right: Pane,
+    active: Side,
+    input: String,
+    /// Byte index into `input`, always on a character boundary.
+    input_cursor: usize,
+    status_message: Option<String>,
+    sort_mode: SortMode,
+    show_hidden: bool,
+    preview: Option<Preview>,
+    /// Lines of the preview scrolled past, reset when another file is selected.
+    preview_scroll: u16,
+    /// The image protocol the terminal speaks, if any, for drawing image previews.
+    graphics: Option<Graphics>,
+    /// Where `draw` left room for the previewed image, drawn over it by `show_image`.
+    image_area: Option<(PathBuf, Rect)>,
+    /// The image currently on screen and where, so it is only sent again when it changes.
+    shown_image: Option<(PathBuf, Rect)>,
+    fzf_requested_at: Option<Instant>,
+    /// Bumped on every input change so that results of older queries can be told apart.
+    fzf_generation: u64,
+    /// The generation of the last query that came back; behind `fzf_generation` while one
+    /// is in flight.
+    fzf_answered: u64,
+    builtin_matcher: bool,
+    fzf_max_depth: usize,
+    fzf_case: CaseMode,
+    fzf_files: bool,
+    keymap: HashMap<Key, Action>,
+    theme: Theme,
+    last_click: Option<(Instant, Side, usize)>,
+    bookmarks: Vec<String>,
+    bookmark_list_state: ListState,
+    listing_sender: ListingSender,
+    listing_receiver: mpsc::UnboundedReceiver<Listing>,
+    search_sender: mpsc::UnboundedSender<(u64, Vec<PathBuf>)>,
+    search_receiver: mpsc::UnboundedReceiver<(u64, Vec<PathBuf>)>,
+    size_sender: mpsc::UnboundedSender<(PathBuf, Option<u64>)>,
+    size_receiver: mpsc::UnboundedReceiver<(PathBuf, Option<u64>)>,
+    copy_sender: mpsc::UnboundedSender<CopyUpdate>,
+    copy_receiver: mpsc::UnboundedReceiver<CopyUpdate>,
+    fzf_sender: FzfSender,
+    fzf_receiver: FzfReceiver,
+    /// The query in flight, aborted when a newer one goes out.
+    fzf_task: Option<JoinHandle<()>>,
+    listing_generation: u64,
+    /// Set by `g` until the key that completes the sequence.
+    pending_key: Option<Action>,
+    wrap_navigation: bool,
+    /// Entering a symlinked directory goes to its target instead of below the link.
+    follow_symlinks: bool,
+    search: Option<Search>,
+    show_permissions: bool,
+    owners: Owners,
+    time_format: TimeFormat,
+    /// Deletes skip the trash.
+    permanent_delete: bool,
+    use_icons: bool,
+    syntax_highlight: bool,
+    /// Marks entries missing from or differing from the other pane.
+    compare: bool,
+    /// Started with `--pick`, so Enter picks the directory instead of opening.
+    pick: bool,
+    picked: Option<PathBuf>,
+    size_job: Option<SizeJob>,
+    /// Only one copy runs at a time; Esc cancels it.
+    copy_job: Option<CopyJob>,
+    /// Set with `E`; `t` turns the filter on and off.
+    extensions: Vec<String>,
+    filter_extensions: bool,
+    /// The entry that was highlighted in each directory visited, by directory.
+    last_selected: HashMap<PathBuf, PathBuf>,
+    listing_cache: ListingCache,
+    /// The left pane's share of the width in percent.
+    split_ratio: u16,
+    /// Undoable operations, most recent last.
+    history: Vec<Operation>,
+}
+
+impl State {
+    /// A state showing nothing yet; `load_pane` reads the panes in the background.
+    pub fn new(config: Config, left_path: &str, right_path: &str) -> State {
+        let (listing_sender, listing_receiver) = mpsc::unbounded_channel();
+        let (search_sender, search_receiver) = mpsc::unbounded_channel();
+        let (size_sender, size_receiver) = mpsc::unbounded_channel();
+        let (copy_sender, copy_receiver) = mpsc::unbounded_channel();
+        let (fzf_sender, fzf_receiver) = mpsc::unbounded_channel();
+        State {
+            input: "".to_string(),
+            input_cursor: 0,
+            mode: Mode::Normal,
+            left: Pane::new(left_path),
+            right: Pane::new(right_path),
+            active: Side::Left,
+            status_message: None,
+            sort_mode: config.sort_mode,
+            show_hidden: config.show_hidden,
+            preview: None,
+            preview_scroll: 0,
+            graphics: image::detect_graphics(),
+            image_area: None,
+            shown_image: None,
+            fzf_requested_at: None,
+            fzf_generation: 0,
+            fzf_answered: 0,
+            // the fzf pipeline relies on Unix find, which Windows doesn't have
+            builtin_matcher: config.builtin_matcher || !cfg!(unix),
+            fzf_max_depth: config.fzf_max_depth,
+            fzf_case: config.fzf_case,
+            fzf_files: config.fzf_files,
+            keymap: config.keymap,
+            theme: config.theme,
+            last_click: None,
+            bookmarks: load_bookmarks(),
+            bookmark_list_state: ListState::default(),
+            listing_sender,
+            listing_receiver,
+            search_sender,
+            search_receiver,
+            size_sender,
+            size_receiver,
+            copy_sender,
+            copy_receiver,
+            fzf_sender,
+            fzf_receiver,
+            fzf_task: None,
+            listing_generation: 0,
+            pending_key: None,
+            wrap_navigation: config.wrap_navigation,
+            follow_symlinks: config.follow_symlinks,
+            search: None,
+            show_permissions: config.show_permissions,
+            owners: Owners::load(),
+            time_format: config.time_format,
+            permanent_delete: config.permanent_delete,
+            use_icons: config.use_icons,
+            syntax_highlight: config.syntax_highlight,
+            compare: false,
+            pick: false,
+            picked: None,
+            size_job: None,
+            copy_job: None,
+            extensions: vec![],
+            filter_extensions: false,
+            last_selected: HashMap::new(),
+            listing_cache: ListingCache::default(),
+            split_ratio: 50,
+            history: vec![],
+        }
+    }
+
+    /// Takes in whatever background work has finished: listings, search results, sizes,
+    /// copies and suggestions. Also sends the suggestion query once the input has settled.
+    pub fn receive_updates(&mut self) {
+        self.send_fzf_query();
+        while let Ok((side, generation, result)) = self.fzf_receiver.try_recv() {
+            // results for anything but the latest input are stale
+            if generation == self.fzf_generation {
+                self.fzf_answered = generation;
+                match result {
+                    Ok(items) => {
+                        self.pane_mut(side).suggestions = Suggestions {
+                            items,
+                            list_state: ListState::default(),
+                        };
+                    }
+                    Err(e) => self.status_message = Some(e),
+                }
+            }
+        }
+        while let Ok((generation, results)) = self.search_receiver.try_recv() {
+            if let Some(search) = self.search.as_mut() {
+                if search.generation == generation {
+                    let selected = (!results.is_empty()).then_some(0);
+                    search.list_state.select(selected);
+                    search.results = Some(results);
+                }
+            }
+        }
+        while let Ok(listing) = self.listing_receiver.try_recv() {
+            self.finish_listing(listing);
+        }
+        if let Some(job) = &self.size_job {
+            if self.pane(job.side).path != job.dir {
+                job.cancel.store(true, atomic::Ordering::Relaxed);
+                self.status_message =
+                    Some(format!("Cancelled size of {}", display_name(&job.path)));
+                self.size_job = None;
+            }
+        }
+        while let Ok((path, size)) = self.size_receiver.try_recv() {
+            let (Some(job), Some(size)) = (&self.size_job, size) else {
+                continue;
+            };
+            // a job that was replaced can still finish before it sees its cancel flag
+            if job.path == path {
+                let side = job.side;
+                self.status_message =
+                    Some(format!("{}: {}", display_name(&path), format_size(size)));
+                self.pane_mut(side).dir_sizes.insert(path, size);
+                self.size_job = None;
+            }
+        }
+        while let Ok(update) = self.copy_receiver.try_recv() {
+            match update {
+                CopyUpdate::Progress(progress) => {
+                    if let Some(job) = self.copy_job.as_mut() {
+                        job.progress = progress;
+                    }
+                }
+                CopyUpdate::Finished(message, copied) => {
+                    self.status_message = Some(message);
+                    self.record(copied);
+                    if let Some(job) = self.copy_job.take() {
+                        self.reload_pane(job.destination, None);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn active(&self) -> Side {
+        self.active
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message.as_deref()
+    }
+
+    pub fn pane(&self, side: Side) -> &Pane {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    pub fn active_pane(&self) -> &Pane {
+        self.pane(self.active)
+    }
+
+    fn pane_mut(&mut self, side: Side) -> &mut Pane {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+
+    fn active_pane_mut(&mut self) -> &mut Pane {
+        self.pane_mut(self.active)
+    }
+
+    fn fzf_searching(&self) -> bool {
+        self.fzf_answered != self.fzf_generation
+    }
+
+    /// None while a query is in flight, as the suggestions shown are then a placeholder.
+    fn selected_suggestion(&self, side: Side) -> Option<String> {
+        if self.fzf_searching() {
+            return None;
+        }
+        let suggestions = &self.pane(side).suggestions;
+        let index = suggestions.list_state.selected()?;
+        suggestions.items.get(index).cloned()
+    }
+
+    fn list_options(&self) -> ListOptions {
+        ListOptions {
+            sort_mode: self.sort_mode,
+            show_hidden: self.show_hidden,
+            extensions: self.filter_extensions.then(|| self.extensions.clone()),
+        }
+    }
+
+    fn set_sort_mode(&mut self, sort_mode: SortMode) {
+        self.sort_mode = sort_mode;
+        self.left.resort(sort_mode);
+        self.right.resort(sort_mode);
+        self.status_message = Some(format!("Sort: {}", sort_mode.label()));
+    }
+
+    /// Creates `name` in the active pane's directory and highlights it.
+    fn make_dir(&mut self, name: &str) {
+        let dir = match name_in(Path::new(&self.active_pane().path), name) {
+            Ok(dir) => dir,
+            Err(e) => {
+                self.status_message = Some(format!("Cannot create {:?}: {}", name, e));
+                return;
+            }
+        };
+        self.status_message = Some(match create_dir(&dir) {
+            Ok(_) => {
+                self.record(Operation::CreatedDir(dir.clone()));
+                self.reload_pane(self.active, Some(dir.clone()));
+                format!("Created {}", display_name(&dir))
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                format!("{} already exists", display_name(&dir))
+            }
+            Err(e) => format!("Failed to create {}: {}", display_name(&dir), e),
+        });
+    }
+
+    /// Creates an empty file called `name` in the active pane's directory and highlights it.
+    fn make_file(&mut self, name: &str) {
+        let file = match name_in(Path::new(&self.active_pane().path), name) {
+            Ok(file) => file,
+            Err(e) => {
+                self.status_message = Some(format!("Cannot create {:?}: {}", name, e));
+                return;
+            }
+        };
+        self.status_message = Some(match File::create_new(&file) {
+            Ok(_) => {
+                self.record(Operation::CreatedFile(file.clone()));
+                self.reload_pane(self.active, Some(file.clone()));
+                format!("Created {}", display_name(&file))
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                format!("{} already exists", display_name(&file))
+            }
+            Err(e) => format!("Failed to create {}: {}", display_name(&file), e),
+        });
+    }
+
+    fn run_command(&mut self, command: command::Command) {
+        match command {
+            command::Command::Mkdir(_)
+            | command::Command::Touch(_)
+            | command::Command::Delete
+            | command::Command::Chmod(_)
+                if self.active_pane().archive.is_some() =>
+            {
+                self.status_message = Some(READ_ONLY.to_string());
+            }
+            command::Command::Mkdir(name) => self.make_dir(&name),
+            command::Command::Touch(name) => self.make_file(&name),
+            command::Command::Cd(path) => {
+                // relative paths are taken from the active pane
+                let path = PathBuf::from(&self.active_pane().path).join(expand_tilde(&path));
+                self.load_pane(self.active, path.to_string_lossy().to_string());
+            }
+            command::Command::Delete if self.active_pane().targets().is_empty() => {
+                self.status_message = Some("Nothing to delete".to_string());
+            }
+            command::Command::Delete => self.mode = Mode::Confirm(Confirmation::Delete),
+            command::Command::Sort(sort_mode) => self.set_sort_mode(sort_mode),
+            command::Command::Chmod(_) if self.active_pane().targets().is_empty() => {
+                self.status_message = Some("Nothing to chmod".to_string());
+            }
+            command::Command::Chmod(mode) => {
+                let targets = self.active_pane().targets();
+                let (message, _) = run_batch(&targets, ("Changed mode of", "chmod"), |path| {
+                    set_mode(path, mode)
+                });
+                self.status_message = Some(message);
+                self.active_pane_mut().marked.clear();
+                self.reload_pane(self.active, None);
+            }
+        }
+    }
+
+    fn record(&mut self, operation: Operation) {
+        let empty = match &operation {
+            Operation::Copied(copies) => copies.is_empty(),
+            Operation::Moved(paths) | Operation::Trashed(paths) => paths.is_empty(),
+            Operation::Renamed(..) | Operation::CreatedDir(_) | Operation::CreatedFile(_) => false,
+        };
+        if empty {
+            return;
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.history.push(operation);
+    }
+
+    /// Background work that quitting would throw away, for the quit confirmation.
+    fn pending_tasks(&self) -> Vec<String> {
+        let mut tasks = vec![];
+        if let Some(job) = &self.size_job {
+            tasks.push(format!("Size of {}", display_name(&job.path)));
+        }
+        if let Some(job) = &self.copy_job {
+            tasks.push(match job.entries {
+                1 => "Copy".to_string(),
+                entries => format!("Copy of {} entries", entries),
+            });
+        }
+        if self
+            .search
+            .as_ref()
+            .is_some_and(|search| search.results.is_none())
+        {
+            tasks.push("Search".to_string());
+        }
+        tasks
+    }
+
+    /// Replaces the input with the cursor at its end.
+    fn set_input(&mut self, input: String) {
+        self.input_cursor = input.len();
+        self.input = input;
+    }
+
+    fn insert_at_cursor(&mut self, c: char) {
+        self.input.insert(self.input_cursor, c);
+        self.input_cursor += c.len_utf8();
+    }
+
+    /// Backspace.
+    fn delete_before_cursor(&mut self) {
+        if let Some(c) = self.input[..self.input_cursor].chars().next_back() {
+            self.input_cursor -= c.len_utf8();
+            self.input.remove(self.input_cursor);
+        }
+    }
+
+    /// Delete.
+    fn delete_at_cursor(&mut self) {
+        if self.input_cursor < self.input.len() {
+            self.input.remove(self.input_cursor);
+        }
+    }
+
+    fn move_cursor(&mut self, code: KeyCode) {
+        self.input_cursor = match code {
+            KeyCode::Left => self.input[..self.input_cursor]
+                .chars()
+                .next_back()
+                .map_or(0, |c| self.input_cursor - c.len_utf8()),
+            KeyCode::Right => self.input[self.input_cursor..]
+                .chars()
+                .next()
+                .map_or(self.input.len(), |c| self.input_cursor + c.len_utf8()),
+            KeyCode::Home => 0,
+            KeyCode::End => self.input.len(),
+            _ => self.input_cursor,
+        };
+    }
+
+    /// Queries suggestions for the input once it has stopped changing for `FZF_DEBOUNCE`.
+    fn request_fzf(&mut self) {
+        self.fzf_generation += 1;
+        self.fzf_requested_at = Some(Instant::now());
+    }
+
+    fn send_fzf_query(&mut self) {
+        let Some(requested_at) = self.fzf_requested_at else {
+            return;
+        };
+        if requested_at.elapsed() < FZF_DEBOUNCE {
+            return;
+        }
+        self.fzf_requested_at = None;
+        // dropping the old query's fzf process kills it
+        if let Some(task) = self.fzf_task.take() {
+            task.abort();
+        }
+        let side = match self.mode {
+            Mode::Edit(field) => field.side(),
+            _ => None,
+        };
+        match side {
+            Some(side) => {
+                self.fzf_task = Some(spawn_fzf_query(
+                    side,
+                    self.input.clone(),
+                    self.fzf_generation,
+                    self.builtin_matcher,
+                    self.suggestion_options(),
+                    self.fzf_sender.clone(),
+                ))
+            }
+            // the prompt was closed before the query went out
+            None => self.fzf_answered = self.fzf_generation,
+        }
+    }
+
+    /// Forgets what the path prompt for `side` suggested, along with any query still on
+    /// its way, so that a prompt opened again starts without a selection.
+    fn clear_suggestions(&mut self, side: Side) {
+        self.pane_mut(side).suggestions = Suggestions::default();
+        self.fzf_requested_at = None;
+        self.fzf_generation += 1;
+        self.fzf_answered = self.fzf_generation;
+    }
+
+    fn suggestion_options(&self) -> SuggestionOptions {
+        SuggestionOptions {
+            max_depth: self.fzf_max_depth,
+            case: self.fzf_case,
+            files: self.fzf_files,
+        }
+    }
+
+    /// Lists a directory or archive from the pane, resolving a symlink if asked to.
+    fn enter(&mut self, side: Side, entry: &Entry) {
+        let path = match (&entry.link, self.follow_symlinks) {
+            (Some(_), true) => entry.path.canonicalize().unwrap_or(entry.path.clone()),
+            _ => entry.path.clone(),
+        };
+        self.request_listing(side, path, None);
+    }
+
+    pub fn load_pane(&mut self, side: Side, path: String) {
+        self.request_listing(side, expand_tilde(&path), None);
+    }
+
+    /// Loads the parent directory with the one we came from highlighted.
+    fn load_parent(&mut self, side: Side) {
+        let current = PathBuf::from(&self.pane(side).path);
+        match current.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                self.request_listing(side, parent.to_path_buf(), Some(current.clone()))
+            }
+            // above a drive root, like C:\, Windows has the list of drives
+            None if cfg!(windows) && current != Path::new(DRIVES) => {
+                self.request_listing(side, PathBuf::from(DRIVES), Some(current.clone()))
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads `path` on a blocking thread; `run` shows it once the listing arrives.
+    fn request_listing(&mut self, side: Side, path: PathBuf, select: Option<PathBuf>) {
+        let options = self.list_options();
+        self.listing_generation += 1;
+        let generation = self.listing_generation;
+        let pane = self.pane_mut(side);
+        pane.generation = generation;
+        pane.loading = true;
+        if let Some((modified, contents)) = self.listing_cache.get(&path, &options) {
+            self.finish_listing(Listing {
+                generation,
+                path,
+                select,
+                options,
+                modified: None,
+                result: Ok(contents),
+            });
+            self.pane_mut(side).modified = Some(modified);
+            return;
+        }
+        let sender = self.listing_sender.clone();
+        tokio::task::spawn_blocking(move || {
+            // taken first so that a change made during the read counts as newer
+            let modified = metadata(&path).and_then(|m| m.modified()).ok();
+            let result = read_path_content(&path, options.clone());
+            sender
+                .send(Listing {
+                    generation,
+                    path,
+                    select,
+                    options,
+                    modified,
+                    result,
+                })
+                .ok();
+        });
+    }
+
+    fn finish_listing(&mut self, listing: Listing) {
+        // generations are unique across both panes, so this also finds the right pane after
+        // a swap; no match means a newer request has been made since
+        let Some(side) = [Side::Left, Side::Right]
+            .into_iter()
+            .find(|side| self.pane(*side).generation == listing.generation)
+        else {
+            return;
+        };
+        let pane = self.pane_mut(side);
+        pane.loading = false;
+        if let (Ok(_), Some(selected)) = (&listing.result, pane.selected_path()) {
+            let dir = PathBuf::from(&pane.path);
+            self.last_selected.insert(dir, selected);
+        }
+        // coming back to a directory highlights what was highlighted when we left it
+        let select = listing
+            .select
+            .or_else(|| self.last_selected.get(&listing.path).cloned());
+        if let (Ok(contents), Some(modified)) = (&listing.result, listing.modified) {
+            self.listing_cache.insert(
+                listing.path.clone(),
+                modified,
+                listing.options,
+                contents.clone(),
+            );
+        }
+        let pane = self.pane_mut(side);
+        // a failed reload records the mtime too, or the watcher would report the directory
+        // again on every poll until it becomes readable
+        if listing.result.is_ok() || Path::new(&pane.path) == listing.path {
+            pane.modified = listing.modified;
+        }
+        match listing.result {
+            // a reload keeps the marks and the filter
+            Ok(contents) if Path::new(&pane.path) == listing.path => {
+                pane.update(contents);
+                if let Some(select) = select {
+                    pane.select_path(&select);
+                }
+            }
+            Ok(contents) => pane.show(listing.path, contents, select.as_deref()),
+            Err(e) => {
+                self.status_message = Some(format!("Cannot read {}: {}", listing.path.display(), e))
+            }
+        }
+    }
+
+    /// Lists the pane's directory again in the background, then highlights `select`, or
+    /// keeps the highlighted entry when that's None.
+    fn reload_pane(&mut self, side: Side, select: Option<PathBuf>) {
+        // a pending listing will show the new contents anyway, and reading the pane's current
+        // directory instead would undo the navigation it's for
+        if self.pane(side).loading {
+            return;
+        }
+        let dir = PathBuf::from(&self.pane(side).path);
+        // the cached listing misses files that were written to in place
+        self.listing_cache.remove(&dir);
+        let select = select.or_else(|| self.pane(side).selected_path());
+        self.request_listing(side, dir, select);
+    }
+
+    fn reload_panes(&mut self) {
+        self.reload_pane(Side::Left, None);
+        self.reload_pane(Side::Right, None);
+    }
+}
+
+/// Restores the terminal before a panic message is printed, which would otherwise land on
+/// the alternate screen and leave the shell in raw mode.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // run and draw are on the main thread; tokio catches panics in background tasks
+        // and the UI keeps going, so those leave the terminal alone
+        if std::thread::current().name() == Some("main") {
+            disable_raw_mode().ok();
+            execute!(
+                io::stderr(),
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                DisableBracketedPaste,
+                Show
+            )
+            .ok();
+        }
+        default_hook(info);
+    }));
+}
+
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stderr>>, Box<dyn Error>> {
+    // stdout is left for the picked path, like fzf does
+    let mut stderr = io::stderr();
+    enable_raw_mode()?;
+    execute!(
+        stderr,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    Ok(Terminal::new(CrosstermBackend::new(stderr))?)
+}
+
+fn restore_terminal(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+) -> Result<(), Box<dyn Error>> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    Ok(terminal.show_cursor()?)
+}
+
+fn resume_terminal(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+) -> Result<(), Box<dyn Error>> {
+    enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    Ok(terminal.clear()?)
+}
+
+async fn edit_files(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    state: &mut State,
+    paths: &[PathBuf],
+) {
+    if paths.iter().any(|path| split_archive_path(path).is_some()) {
+        state.status_message = Some(READ_ONLY.to_string());
+        return;
+    }
+    if let Err(e) = open_in_editor(terminal, paths).await {
+        let name = match paths {
+            [path] => display_name(path),
+            paths => format!("{} files", paths.len()),
+        };
+        state.status_message = Some(format!("Failed to open {}: {}", name, e));
+    }
+    state.reload_panes();
+}
+
+/// Opens all of `paths` in one editor, e.g. `vi one.txt two.txt`.
+async fn open_in_editor(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    paths: &[PathBuf],
+) -> Result<(), Box<dyn Error>> {
+    let fallback = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| fallback.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    restore_terminal(terminal)?;
+    let status = Command::new(program)
+        .args(words)
+        .args(paths)
+        .stdout(io::stderr())
+        .status()
+        .await;
+    // the TUI has to come back whatever happened to the editor
+    resume_terminal(terminal)?;
+
+    match status? {
+        status if status.success() => Ok(()),
+        status => Err(format!("{} exited with {}", program, status).into()),
+    }
+}
+
+/// Runs `$SHELL` in `dir` with the TUI suspended until it exits.
+async fn open_shell(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let (variable, fallback) = if cfg!(windows) {
+        ("COMSPEC", "cmd")
+    } else {
+        ("SHELL", "sh")
+    };
+    let shell = std::env::var(variable).unwrap_or_else(|_| fallback.to_string());
+
+    restore_terminal(terminal)?;
+    let status = Command::new(&shell)
+        .current_dir(dir)
+        .stdout(io::stderr())
+        .status()
+        .await;
+    resume_terminal(terminal)?;
+    // the exit status is the last command's, so only failing to start counts
+    status.map_err(|e| missing_binary(e, &shell))?;
+    Ok(())
+}
+
+fn open_with_default(path: &Path) -> io::Result<()> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("open", &[])
+    } else if cfg!(target_os = "windows") {
+        ("cmd", &["/C", "start", ""])
+    } else {
+        ("xdg-open", &[])
+    };
+    Command::new(program)
+        .args(args)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| missing_binary(e, program))?;
+    Ok(())
+}
+
+/// Pipes `text` into the first clipboard tool that is installed.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    for (program, args) in tools {
+        let spawned = std::process::Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        // without a display the tools start but fail, e.g. over ssh
+        return match child.wait()?.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!(
+                "{} has no clipboard to copy to",
+                program
+            ))),
+        };
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard tool found in PATH",
+    ))
+}
+
+pub async fn run(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    state: &mut State,
+) -> Result<(), Box<dyn Error>> {
+    let (watch_sender, mut watch_receiver) = mpsc::unbounded_channel();
+    // stops polling when run returns
+    let watcher = Watcher::new(watch_sender);
+    loop {
+        watcher.watch(
+            [&state.left, &state.right]
+                .into_iter()
+                .filter_map(|pane| Some((PathBuf::from(&pane.path), pane.modified?)))
+                .collect(),
+        );
+        while let Ok(dir) = watch_receiver.try_recv() {
+            for side in [Side::Left, Side::Right] {
+                let pane = state.pane(side);
+                if Path::new(&pane.path) == dir {
+                    state.reload_pane(side, None);
+                }
+            }
+        }
+        state.receive_updates();
+
+        if event::poll(Duration::from_millis(50))? {
+            let event = event::read()?;
+            if let Event::Resize(width, height) = event {
+                // resizing clears the buffers so the draw below repaints everything at the new
+                // size instead of diffing against the old layout
+                terminal.resize(Rect::new(0, 0, width, height))?;
+                state.shown_image = None;
+            }
+            if let Event::Mouse(mouse) = event {
+                handle_mouse(terminal, state, mouse).await;
+            }
+            if let (Event::Paste(text), Mode::Edit(field)) = (&event, state.mode) {
+                // a newline copied along with a path would end up in the input
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    state.insert_at_cursor(c);
+                }
+                if field.is_path() {
+                    state.request_fzf();
+                }
+                if let Field::Filter = field {
+                    let filter = state.input.clone();
+                    state.active_pane_mut().set_filter(filter);
+                }
+            }
+            if let Event::Key(key) = event {
+                let action = handle_key(state, key);
+                // the editor and the shell leave a cleared screen behind
+                if matches!(action, Some(Action::Open | Action::Edit | Action::Shell)) {
+                    state.shown_image = None;
+                }
+                match action {
+                    Some(Action::Quit) => break,
+                    Some(Action::Open) => {
+                        if let Some(path) = state.active_pane().selected_path() {
+                            edit_files(terminal, state, &[path]).await;
+                        }
+                    }
+                    Some(Action::Edit) => {
+                        // the marked files, or the highlighted one
+                        let files: Vec<PathBuf> = state
+                            .active_pane()
+                            .targets()
+                            .into_iter()
+                            .filter(|path| !path.is_dir())
+                            .collect();
+                        if !files.is_empty() {
+                            state.active_pane_mut().marked.clear();
+                            edit_files(terminal, state, &files).await;
+                        }
+                    }
+                    Some(Action::Pick) => {
+                        // inside an archive the archive's directory is picked
+                        if let Some(dir) = state.active_pane().real_dir() {
+                            state.picked = Some(dir);
+                            break;
+                        }
+                    }
+                    Some(Action::Shell) => {
+                        // inside an archive the shell starts next to it
+                        if let Some(dir) = state.active_pane().real_dir() {
+                            if let Err(e) = open_shell(terminal, &dir).await {
+                                state.status_message =
+                                    Some(format!("Failed to start a shell: {}", e));
+                            }
+                            state.reload_panes();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        update_preview(state);
+        draw(terminal, state)?;
+        show_image(terminal, state)?;
+    }
+    Ok(())
+}
+
+/// Draws the previewed image over the room `draw` left for it, with the terminal's image
+/// protocol, which ratatui's cells can't express.
+fn show_image(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    state: &mut State,
+) -> io::Result<()> {
+    let Some(graphics) = state.graphics else {
+        return Ok(());
+    };
+    if state.image_area == state.shown_image {
+        return Ok(());
+    }
+    if state.shown_image.take().is_some() {
+        match graphics {
+            Graphics::Kitty => terminal.backend_mut().write_all(KITTY_DELETE.as_bytes())?,
+            // ratatui only repaints cells that changed, so the whole screen is drawn again
+            Graphics::Iterm => {
+                terminal.clear()?;
+                draw(terminal, state)?;
+            }
+        }
+    }
+    if let Some((path, area)) = &state.image_area {
+        if let Some(escape) = inline_image(graphics, path, area.width, area.height) {
+            let backend = terminal.backend_mut();
+            queue!(backend, MoveTo(area.x, area.y))?;
+            backend.write_all(escape.as_bytes())?;
+        }
+    }
+    Write::flush(terminal.backend_mut())?;
+    state.shown_image = state.image_area.clone();
+    Ok(())
+}
+
+/// Applies a key press to `state`. Returns the action when it needs the terminal, which
+/// only `run` has: quitting, editing files and opening a shell.
+pub fn handle_key(state: &mut State, mut key: KeyEvent) -> Option<Action> {
+    state.status_message = None;
+    // outside the pane view Ctrl-c cancels whatever is open, like Esc
+    if key_of(key) == (KeyCode::Char('c'), KeyModifiers::CONTROL)
+        && !matches!(state.mode, Mode::Normal)
+    {
+        key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+    }
+    match state.mode {
+        Mode::Normal => {
+            // the second key of a sequence like gg is taken as typed
+            let action = match (state.pending_key.take(), key.code) {
+                (Some(Action::Goto), KeyCode::Char('g')) => Some(Action::First),
+                (Some(Action::Goto), KeyCode::Char('p')) => Some(Action::Jump),
+                (Some(Action::Goto), KeyCode::Char('/')) => Some(Action::Root),
+                _ => state.keymap.get(&key_of(key)).copied(),
+            };
+            let Some(action) = action else {
+                // unbound keys do nothing
+                return None;
+            };
+            let action = match action {
+                Action::Open if state.pick => Action::Pick,
+                action => action,
+            };
+            match action {
+                Action::Quit if !state.pending_tasks().is_empty() => {
+                    state.mode = Mode::Confirm(Confirmation::Quit);
+                }
+                Action::Quit => return Some(Action::Quit),
+                Action::First => {
+                    state.active_pane_mut().select_first();
+                }
+                Action::Jump => {
+                    let mut path = state.active_pane().path.clone();
+                    if !path.ends_with(is_separator) {
+                        path.push(MAIN_SEPARATOR);
+                    }
+                    state.set_input(path);
+                    state.mode = Mode::Edit(Field::Jump);
+                }
+                Action::Goto => {
+                    state.pending_key = Some(Action::Goto);
+                }
+                Action::Last => {
+                    state.active_pane_mut().select_last();
+                }
+                Action::EditLeft => {
+                    state.active = Side::Left;
+                    state.clear_suggestions(Side::Left);
+                    state.mode = Mode::Edit(Field::LeftPath);
+                }
+                Action::EditRight => {
+                    state.active = Side::Right;
+                    state.clear_suggestions(Side::Right);
+                    state.mode = Mode::Edit(Field::RightPath);
+                }
+                Action::SwitchPane => {
+                    state.active = state.active.other();
+                }
+                Action::NavDown => {
+                    let wrap = state.wrap_navigation;
+                    state.active_pane_mut().select_next(wrap);
+                }
+                Action::NavUp => {
+                    let wrap = state.wrap_navigation;
+                    state.active_pane_mut().select_previous(wrap);
+                }
+                Action::Enter | Action::Open => {
+                    if let Some(entry) = state.active_pane().selected_entry().cloned() {
+                        if state.active_pane().can_enter(&entry) {
+                            state.enter(state.active, &entry);
+                        } else if action == Action::Open {
+                            return Some(Action::Open);
+                        }
+                    }
+                }
+                Action::Shell => return Some(Action::Shell),
+                Action::Pick if state.pick => return Some(Action::Pick),
+                Action::Pick => {}
+                Action::OpenDefault
+                | Action::Edit
+                | Action::Delete
+                | Action::Rename
+                | Action::NewDir
+                | Action::NewFile
+                    if state.active_pane().archive.is_some() =>
+                {
+                    state.status_message = Some(READ_ONLY.to_string());
+                }
+                Action::Copy | Action::Move
+                    if state.left.archive.is_some() || state.right.archive.is_some() =>
+                {
+                    state.status_message = Some(READ_ONLY.to_string());
+                }
+                Action::OpenDefault => {
+                    if let Some(path) = state.active_pane().selected_path() {
+                        state.status_message = Some(match open_with_default(&path) {
+                            Ok(_) => format!("Opened {}", display_name(&path)),
+                            Err(e) => {
+                                format!("Failed to open {}: {}", display_name(&path), e)
+                            }
+                        });
+                    }
+                }
+                Action::Edit => return Some(Action::Edit),
+                Action::Parent => {
+                    state.load_parent(state.active);
+                }
+                Action::Home => match home_dir() {
+                    Some(home) => state.request_listing(state.active, home, None),
+                    None => state.status_message = Some("No home directory".to_string()),
+                },
+                Action::Root => {
+                    let current = PathBuf::from(&state.active_pane().path);
+                    // / on Unix, the drive's root like C:\ on Windows
+                    if let Some(root) = current.ancestors().last() {
+                        state.request_listing(state.active, root.to_path_buf(), None);
+                    }
+                }
+                Action::Delete if state.active_pane().selected_entry().is_some() => {
+                    state.mode = Mode::Confirm(Confirmation::Delete);
+                }
+                Action::PageDown | Action::PageUp => {
+                    let pane = state.active_pane_mut();
+                    // the list's height inside its borders
+                    let rows = pane.area.height.saturating_sub(2).max(1) as isize;
+                    let rows = if action == Action::PageUp {
+                        -rows
+                    } else {
+                        rows
+                    };
+                    pane.move_selection(rows);
+                }
+                Action::Mark => {
+                    state.active_pane_mut().toggle_mark();
+                    state.active_pane_mut().select_next(false);
+                }
+                Action::Copy if state.copy_job.is_some() => {
+                    state.status_message = Some("A copy is already running".to_string());
+                }
+                Action::Copy => {
+                    let targets = state.active_pane().targets();
+                    if !targets.is_empty() {
+                        let destination = state.active.other();
+                        let dir = PathBuf::from(&state.pane(destination).path);
+                        let cancel = Arc::new(AtomicBool::new(false));
+                        state.copy_job = Some(CopyJob {
+                            destination,
+                            entries: targets.len(),
+                            progress: Progress::default(),
+                            started: Instant::now(),
+                            cancel: cancel.clone(),
+                        });
+                        state.active_pane_mut().marked.clear();
+                        let sender = state.copy_sender.clone();
+                        tokio::task::spawn_blocking(move || {
+                            let progress_sender = sender.clone();
+                            let mut copier = Copier::new(&cancel, |progress| {
+                                progress_sender.send(CopyUpdate::Progress(progress)).ok();
+                            });
+                            let (mut message, copies) =
+                                run_batch(&targets, ("Copied", "copy"), |path| {
+                                    copy_entry(path, &dir, &mut copier)
+                                });
+                            if cancel.load(atomic::Ordering::Relaxed) {
+                                message = format!(
+                                    "Cancelled copy after {} of {} entries",
+                                    copies.len(),
+                                    targets.len()
+                                );
+                            }
+                            let copies = copies.into_iter().map(|(_, copy)| copy).collect();
+                            let copied = Operation::copied(copies);
+                            sender.send(CopyUpdate::Finished(message, copied)).ok();
+                        });
+                    }
+                }
+                Action::Move => {
+                    let targets = state.active_pane().targets();
+                    if !targets.is_empty() {
+                        let destination = state.active.other();
+                        let dir = PathBuf::from(&state.pane(destination).path);
+                        let (message, moves) =
+                            run_batch(&targets, ("Moved", "move"), |path| move_entry(path, &dir));
+                        state.status_message = Some(message);
+                        state.record(Operation::Moved(moves));
+                        state.active_pane_mut().marked.clear();
+                        state.reload_panes();
+                    }
+                }
+                Action::Rename => {
+                    if let Some(name) = state
+                        .active_pane()
+                        .selected_path()
+                        .and_then(|p| p.file_name().map(|n| n.to_owned()))
+                    {
+                        state.set_input(name.to_string_lossy().to_string());
+                        state.mode = Mode::Edit(Field::Rename);
+                    }
+                }
+                Action::CycleSort => {
+                    state.set_sort_mode(state.sort_mode.next());
+                }
+                Action::Compare => {
+                    state.compare = !state.compare;
+                }
+                Action::PreviewDown | Action::PreviewUp => {
+                    if let Some(preview) = &state.preview {
+                        let last = preview.line_count().saturating_sub(1) as u16;
+                        state.preview_scroll = match action {
+                            Action::PreviewDown => (state.preview_scroll + 1).min(last),
+                            _ => state.preview_scroll.saturating_sub(1),
+                        };
+                    }
+                }
+                Action::ToggleHidden => {
+                    state.show_hidden = !state.show_hidden;
+                    state.reload_panes();
+                }
+                Action::ToggleFollowSymlinks => {
+                    state.follow_symlinks = !state.follow_symlinks;
+                    state.status_message = Some(match state.follow_symlinks {
+                        true => "Symlinked directories open at their target".to_string(),
+                        false => "Symlinked directories open below the link".to_string(),
+                    });
+                }
+                Action::NewDir => {
+                    state.mode = Mode::Edit(Field::NewDir);
+                }
+                Action::NewFile => {
+                    state.mode = Mode::Edit(Field::NewFile);
+                }
+                Action::Command => {
+                    state.mode = Mode::Edit(Field::Command);
+                }
+                Action::Search => {
+                    state.mode = Mode::Edit(Field::Search);
+                }
+                Action::Filter => {
+                    state.set_input(state.active_pane().filter.clone());
+                    state.mode = Mode::Edit(Field::Filter);
+                }
+                Action::ClearFilter if state.copy_job.is_some() => {
+                    if let Some(job) = &state.copy_job {
+                        job.cancel.store(true, atomic::Ordering::Relaxed);
+                    }
+                    state.status_message = Some("Cancelling copy…".to_string());
+                }
+                Action::ClearFilter => {
+                    state.active_pane_mut().set_filter(String::new());
+                }
+                Action::Help => {
+                    state.mode = Mode::Help;
+                }
+                Action::Swap => {
+                    std::mem::swap(&mut state.left, &mut state.right);
+                    // stay with the directory that was active, now on the other side
+                    state.active = state.active.other();
+                    // background jobs follow their pane; listings find theirs by generation
+                    if let Some(job) = state.size_job.as_mut() {
+                        job.side = job.side.other();
+                    }
+                    if let Some(job) = state.copy_job.as_mut() {
+                        job.destination = job.destination.other();
+                    }
+                }
+                Action::Mirror => {
+                    let path = state.active_pane().path.clone();
+                    state.load_pane(state.active.other(), path);
+                }
+                Action::DirSize => {
+                    let selected = state.active_pane().selected_entry().cloned();
+                    match selected {
+                        Some(_) if state.active_pane().archive.is_some() => {
+                            state.status_message =
+                                Some("Sizes aren't available inside archives".to_string());
+                        }
+                        Some(entry) if entry.is_dir => {
+                            if let Some(job) = state.size_job.take() {
+                                job.cancel.store(true, atomic::Ordering::Relaxed);
+                            }
+                            let cancel = Arc::new(AtomicBool::new(false));
+                            state.size_job = Some(SizeJob {
+                                side: state.active,
+                                dir: state.active_pane().path.clone(),
+                                path: entry.path.clone(),
+                                cancel: cancel.clone(),
+                            });
+                            state.status_message = Some(format!(
+                                "Calculating size of {}…",
+                                display_name(&entry.path)
+                            ));
+                            let sender = state.size_sender.clone();
+                            tokio::task::spawn_blocking(move || {
+                                let size = dir_size(&entry.path, &cancel);
+                                sender.send((entry.path, size)).ok();
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                Action::ShrinkLeft | Action::GrowLeft => {
+                    let ratio = match action {
+                        Action::ShrinkLeft => state.split_ratio.saturating_sub(SPLIT_STEP),
+                        _ => state.split_ratio + SPLIT_STEP,
+                    };
+                    state.split_ratio = ratio.clamp(SPLIT_RANGE.0, SPLIT_RANGE.1);
+                    state.status_message = Some(format!("Left pane: {}%", state.split_ratio));
+                }
+                Action::EditExtensions => {
+                    state.set_input(state.extensions.join(","));
+                    state.mode = Mode::Edit(Field::Extensions);
+                }
+                Action::ToggleExtensions if state.extensions.is_empty() => {
+                    state.status_message =
+                        Some("No extensions to filter by, set them with E".to_string());
+                }
+                Action::ToggleExtensions => {
+                    state.filter_extensions = !state.filter_extensions;
+                    state.reload_panes();
+                }
+                Action::Refresh => {
+                    // a failed read replaces this with its error
+                    state.status_message = Some("Refreshed".to_string());
+                    state.reload_panes();
+                }
+                Action::CopyPath => {
+                    if let Some(path) = state.active_pane().selected_path() {
+                        let path = std::path::absolute(&path).unwrap_or(path);
+                        state.status_message =
+                            Some(match copy_to_clipboard(&path.to_string_lossy()) {
+                                Ok(_) => "Copied path to clipboard".to_string(),
+                                Err(e) => format!("Failed to copy path: {}", e),
+                            });
+                    }
+                }
+                Action::CopyName => {
+                    if let Some(path) = state.active_pane().selected_path() {
+                        let name = display_name(&path);
+                        state.status_message = Some(match copy_to_clipboard(&name) {
+                            Ok(_) => format!("Copied {} to clipboard", name),
+                            Err(e) => format!("Failed to copy name: {}", e),
+                        });
+                    }
+                }
+                Action::Undo => {
+                    state.status_message = Some(match state.history.pop() {
+                        None => "Nothing to undo".to_string(),
+                        Some(operation) => match operation.undo() {
+                            Ok(_) => format!("Undid {}", operation.describe()),
+                            Err(e) => {
+                                format!("Failed to undo {}: {}", operation.describe(), e)
+                            }
+                        },
+                    });
+                    state.reload_panes();
+                }
+                Action::Bookmark => {
+                    let path = state.active_pane().path.clone();
+                    if state.bookmarks.contains(&path) {
+                        state.status_message = Some(format!("{} is already bookmarked", path));
+                    } else {
+                        state.bookmarks.push(path.clone());
+                        state.status_message = Some(match save_bookmarks(&state.bookmarks) {
+                            Ok(_) => format!("Bookmarked {}", path),
+                            Err(e) => format!("Failed to save bookmarks: {}", e),
+                        });
+                    }
+                }
+                Action::Bookmarks => {
+                    let selected = (!state.bookmarks.is_empty()).then_some(0);
+                    state.bookmark_list_state.select(selected);
+                    state.mode = Mode::Bookmarks;
+                }
+                _ => {}
+            };
+        }
+        Mode::Search => {
+            let Some(search) = state.search.as_mut() else {
+                state.mode = Mode::Normal;
+                return None;
+            };
+            let results = search.results.clone().unwrap_or_default();
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => state.mode = Mode::Normal,
+                KeyCode::Char('j') | KeyCode::Down => {
+                    select_next_wrapping(&mut search.list_state, results.len());
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    select_previous_wrapping(&mut search.list_state, results.len());
+                }
+                KeyCode::Enter => {
+                    let selected = search.list_state.selected();
+                    if let Some(path) = selected.and_then(|i| results.get(i)) {
+                        if let Some(parent) = path.parent() {
+                            state.request_listing(
+                                state.active,
+                                parent.to_path_buf(),
+                                Some(path.clone()),
+                            );
+                        }
+                    }
+                    state.mode = Mode::Normal;
+                }
+                _ => {}
+            }
+        }
+        Mode::Bookmarks => match key.code {
+            KeyCode::Esc | KeyCode::Char('\'') => state.mode = Mode::Normal,
+            KeyCode::Char('j') | KeyCode::Down => {
+                let len = state.bookmarks.len();
+                select_next_wrapping(&mut state.bookmark_list_state, len);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                let len = state.bookmarks.len();
+                select_previous_wrapping(&mut state.bookmark_list_state, len);
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(index) = state.bookmark_list_state.selected() {
+                    state.bookmarks.remove(index);
+                    let len = state.bookmarks.len();
+                    state
+                        .bookmark_list_state
+                        .select((len > 0).then(|| index.min(len - 1)));
+                    if let Err(e) = save_bookmarks(&state.bookmarks) {
+                        state.status_message = Some(format!("Failed to save bookmarks: {}", e));
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                let selected = state.bookmark_list_state.selected();
+                if let Some(path) = selected.and_then(|i| state.bookmarks.get(i)) {
+                    state.load_pane(state.active, path.clone());
+                }
+                state.mode = Mode::Normal;
+            }
+            _ => {}
+        },
+        Mode::Help => {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('?')) {
+                state.mode = Mode::Normal;
+            }
+        }
+        Mode::Confirm(confirmation) => {
+            if key.code == KeyCode::Char('y') {
+                match confirmation {
+                    Confirmation::Delete => {
+                        let targets = state.active_pane().targets();
+                        state.status_message = Some(match state.permanent_delete {
+                            true => run_batch(&targets, ("Deleted", "delete"), delete_entry).0,
+                            false => {
+                                let (message, trashed) =
+                                    run_batch(&targets, ("Trashed", "trash"), move_to_trash);
+                                state.record(Operation::Trashed(trashed));
+                                message
+                            }
+                        });
+                        state.active_pane_mut().marked.clear();
+                        state.reload_pane(state.active, None);
+                    }
+                    Confirmation::Quit => return Some(Action::Quit),
+                }
+            }
+            state.mode = Mode::Normal;
+        }
+        Mode::Edit(field) => {
+            if let (KeyCode::Enter, Some(side)) = (key.code, field.side()) {
+                if let Some(suggestion) = state.selected_suggestion(side) {
+                    state.set_input(suggestion);
+                }
+            }
+            match key.code {
+                KeyCode::Esc => {
+                    if let Field::Filter = field {
+                        state.active_pane_mut().set_filter(String::new());
+                    }
+                    if let Some(side) = field.side() {
+                        state.clear_suggestions(side);
+                    }
+                    state.set_input(String::new());
+                    state.mode = Mode::Normal;
+                }
+                KeyCode::Up | KeyCode::Down
+                    if field.is_path() && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    state.fzf_max_depth = match key.code {
+                        KeyCode::Up => state.fzf_max_depth + 1,
+                        _ => state.fzf_max_depth.saturating_sub(1).max(1),
+                    };
+                    state.status_message = Some(format!("Search depth: {}", state.fzf_max_depth));
+                    state.request_fzf();
+                }
+                KeyCode::Char('s')
+                    if field.is_path() && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    state.fzf_case = state.fzf_case.next();
+                    state.status_message = Some(format!("Suggestions: {}", state.fzf_case.label()));
+                    state.request_fzf();
+                }
+                KeyCode::Char('f')
+                    if field.is_path() && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    state.fzf_files = !state.fzf_files;
+                    state.status_message =
+                        Some(format!("Suggestions: {}", files_label(state.fzf_files)));
+                    state.request_fzf();
+                }
+                KeyCode::Up if matches!(field, Field::Filter) => {
+                    let wrap = state.wrap_navigation;
+                    state.active_pane_mut().select_previous(wrap);
+                }
+                KeyCode::Down if matches!(field, Field::Filter) => {
+                    let wrap = state.wrap_navigation;
+                    state.active_pane_mut().select_next(wrap);
+                }
+                KeyCode::Up | KeyCode::Down if field.is_path() => {
+                    let side = field.side().unwrap_or(state.active);
+                    let suggestions = &mut state.pane_mut(side).suggestions;
+                    let len = suggestions.items.len();
+                    match key.code {
+                        KeyCode::Up => select_previous_wrapping(&mut suggestions.list_state, len),
+                        _ => select_next_wrapping(&mut suggestions.list_state, len),
+                    }
+                }
+                KeyCode::Tab if matches!(field, Field::Jump) => {
+                    let base = PathBuf::from(&state.active_pane().path);
+                    let completion = complete_dir(&state.input, &base);
+                    state.set_input(completion.input);
+                    state.status_message = match completion.candidates.len() {
+                        0 => Some("No matching directory".to_string()),
+                        1 => None,
+                        _ => Some(completion.candidates.join("  ")),
+                    };
+                }
+                KeyCode::Tab if field.is_path() => {
+                    let side = field.side().unwrap_or(state.active);
+                    if let Some(suggestion) = state.selected_suggestion(side) {
+                        state.set_input(suggestion);
+                        state.request_fzf();
+                    }
+                }
+                // control characters would end up in the input otherwise
+                KeyCode::Char(c)
+                    if !key
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                {
+                    state.insert_at_cursor(c);
+                    if field.is_path() {
+                        state.request_fzf();
+                    }
+                    if let Field::Filter = field {
+                        let filter = state.input.clone();
+                        state.active_pane_mut().set_filter(filter);
+                    }
+                }
+                KeyCode::Enter if field.is_path() && !expand_tilde(&state.input).exists() => {
+                    state.status_message = Some(format!("No such path: {}", state.input));
+                }
+                KeyCode::Enter
+                    if matches!(field, Field::Jump)
+                        && !PathBuf::from(&state.active_pane().path)
+                            .join(expand_tilde(&state.input))
+                            .is_dir() =>
+                {
+                    state.status_message = Some(format!("Not a directory: {}", state.input));
+                }
+                KeyCode::Enter => {
+                    let input = state.input.clone();
+                    match field {
+                        Field::LeftPath | Field::RightPath => {
+                            let side = field.side().unwrap_or(state.active);
+                            let path = expand_tilde(&input);
+                            // a file shows up highlighted in its directory
+                            match (path.is_dir(), path.parent()) {
+                                (false, Some(parent)) => state.request_listing(
+                                    side,
+                                    parent.to_path_buf(),
+                                    Some(path.clone()),
+                                ),
+                                _ => state.load_pane(side, input),
+                            }
+                        }
+                        Field::Rename => {
+                            if let Some(entry) = state.active_pane().selected_path() {
+                                match rename_entry(&entry, &input) {
+                                    Ok(renamed) => {
+                                        state.status_message = Some(format!(
+                                            "Renamed {} to {}",
+                                            display_name(&entry),
+                                            display_name(&renamed)
+                                        ));
+                                        state.record(Operation::Renamed(
+                                            entry.clone(),
+                                            renamed.clone(),
+                                        ));
+                                        state.reload_pane(state.active, Some(renamed.clone()));
+                                    }
+                                    Err(e) => {
+                                        state.status_message = Some(format!(
+                                            "Failed to rename {}: {}",
+                                            display_name(&entry),
+                                            e
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                        // the filter is already applied while typing
+                        Field::Filter => {}
+                        Field::Search => {
+                            let root = PathBuf::from(&state.active_pane().path);
+                            let generation = state.search.as_ref().map_or(0, |s| s.generation + 1);
+                            state.search = Some(Search {
+                                root: root.clone(),
+                                query: input.clone(),
+                                generation,
+                                results: None,
+                                list_state: ListState::default(),
+                            });
+                            let sender = state.search_sender.clone();
+                            tokio::task::spawn_blocking(move || {
+                                let results = search_names(&input, &root);
+                                sender.send((generation, results)).ok();
+                            });
+                        }
+                        Field::NewDir => state.make_dir(&input),
+                        Field::NewFile => state.make_file(&input),
+                        Field::Extensions => {
+                            state.extensions = input
+                                .split(',')
+                                .map(|extension| {
+                                    extension.trim().trim_start_matches('.').to_lowercase()
+                                })
+                                .filter(|extension| !extension.is_empty())
+                                .collect();
+                            // an empty list turns the filter off
+                            state.filter_extensions = !state.extensions.is_empty();
+                            state.reload_panes();
+                        }
+                        Field::Jump => {
+                            let path =
+                                PathBuf::from(&state.active_pane().path).join(expand_tilde(&input));
+                            state.load_pane(state.active, path.to_string_lossy().to_string());
+                        }
+                        Field::Command => match parse_command(&input) {
+                            Ok(command) => state.run_command(command),
+                            Err(e) => state.status_message = Some(e),
+                        },
+                    }
+
+                    state.set_input(String::new());
+                    // commands may have switched to another mode already
+                    state.mode = match state.mode {
+                        Mode::Edit(Field::Search) => Mode::Search,
+                        Mode::Edit(_) => Mode::Normal,
+                        mode => mode,
+                    };
+                }
+                KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => {
+                    state.move_cursor(key.code);
+                }
+                KeyCode::Backspace | KeyCode::Delete => {
+                    match key.code {
+                        KeyCode::Backspace => state.delete_before_cursor(),
+                        _ => state.delete_at_cursor(),
+                    }
+                    if field.is_path() {
+                        state.request_fzf();
+                    }
+                    if let Field::Filter = field {
+                        let filter = state.input.clone();
+                        state.active_pane_mut().set_filter(filter);
+                    }
+                }
+                _ => {}
+            };
+        }
+    };
+    None
+}
+
+async fn handle_mouse(
+    terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+    state: &mut State,
+    mouse: MouseEvent,
+) {
+    if !matches!(state.mode, Mode::Normal) {
+        return;
+    }
+    let Some(side) = [Side::Left, Side::Right]
+        .into_iter()
+        .find(|side| state.pane(*side).contains(mouse.column, mouse.row))
+    else {
+        return;
+    };
+    match mouse.kind {
+        MouseEventKind::ScrollDown => state.pane_mut(side).select_next(false),
+        MouseEventKind::ScrollUp => state.pane_mut(side).select_previous(false),
+        MouseEventKind::Down(MouseButton::Left) => {
+            let Some(index) = state.pane(side).index_at(mouse.row) else {
+                return;
+            };
+            state.active = side;
+            state.pane_mut(side).list_state.select(Some(index));
+
+            let now = Instant::now();
+            let double_click = state.last_click.is_some_and(|(at, clicked_side, clicked)| {
+                clicked_side == side && clicked == index && now - at < DOUBLE_CLICK
+            });
+            state.last_click = (!double_click).then_some((now, side, index));
+            if !double_click {
+                return;
+            }
+            if let Some(entry) = state.pane(side).selected_entry().cloned() {
+                if state.pane(side).can_enter(&entry) {
+                    state.enter(side, &entry);
+                } else {
+                    edit_files(terminal, state, &[entry.path]).await;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Applies `operation` to every path and sums the outcome up for the status line, also
+/// returning the successful results. `verbs` is the past and present tense, e.g.
+/// ("Copied", "copy").
+fn run_batch<T>(
+    paths: &[PathBuf],
+    verbs: (&str, &str),
+    mut operation: impl FnMut(&Path) -> io::Result<T>,
+) -> (String, Vec<(PathBuf, T)>) {
+    let (done, verb) = verbs;
+    let mut done_paths = vec![];
+    let mut failures = vec![];
+    for path in paths {
+        match operation(path) {
+            Ok(result) => done_paths.push((path.clone(), result)),
+            Err(e) => failures.push((path, e)),
+        }
+    }
+    let message = match (paths, failures.first()) {
+        ([path], None) => format!("{} {}", done, display_name(path)),
+        (_, None) => format!("{} {} entries", done, paths.len()),
+        ([_], Some((path, e))) => format!("Failed to {} {}: {}", verb, display_name(path), e),
+        (_, Some((path, e))) => format!(
+            "Failed to {} {}: {} ({} of {} failed)",
+            verb,
+            display_name(path),
+            e,
+            failures.len(),
+            paths.len()
+        ),
+    };
+    (message, done_paths)
+}
+
+fn select_next_wrapping(list_state: &mut ListState, len: usize) {
+    let next = match list_state.selected() {
+        _ if len == 0 => None,
+        Some(v) => Some((v + 1) % len),
+        None => Some(0),
+    };
+    list_state.select(next);
+}
+
+fn select_previous_wrapping(list_state: &mut ListState, len: usize) {
+    let previous = match list_state.selected() {
+        _ if len == 0 => None,
+        Some(0) | None => Some(len - 1),
+        Some(v) => Some(v.min(len) - 1),
+    };
+    list_state.select(previous);
+}
+
+fn spawn_fzf_query(
+    side: Side,
+    query: String,
+    generation: u64,
+    builtin_matcher: bool,
+    options: SuggestionOptions,
+    sender: FzfSender,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let result = match builtin_matcher {
+            true => {
+                let expanded = expand_tilde(&query);
+                tokio::task::spawn_blocking(move || {
+                    let root = expanded
+                        .ancestors()
+                        .find(|dir| dir.is_dir())
+                        .unwrap_or(Path::new("."));
+                    fuzzy_suggestions(&expanded.to_string_lossy(), root, options)
+                })
+                .await
+                .map_err(|e| e.to_string())
+            }
+            false => run_fzf_query(&query, options)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+        sender.send((side, generation, result)).ok();
+    })
+}
+
+fn update_preview(state: &mut State) {
+    let selected = state
+        .active_pane()
+        .selected_entry()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path.clone());
+    match selected {
+        Some(path) => {
+            if state.preview.as_ref().map(|p| &p.path) != Some(&path) {
+                let content = match state.active_pane().archive {
+                    Some(_) => PreviewContent::Unreadable("No preview inside archives".to_string()),
+                    None => read_preview(&path),
+                };
+                let content = match (content, highlight::syntax_for(&path)) {
+                    (PreviewContent::Text(lines), Some(syntax)) if state.syntax_highlight => {
+                        PreviewContent::Code(highlight::highlight(&lines, syntax))
+                    }
+                    (content, _) => content,
+                };
+                state.preview = Some(Preview { path, content });
+                state.preview_scroll = 0;
+            }
+        }
+        None => state.preview = None,
+    }
+}
+
+fn read_preview(path: &Path) -> PreviewContent {
+    if image::is_image(path) {
+        return image_preview(path);
+    }
+    let mut bytes = vec![];
+    let read =
+        File::open(path).and_then(|file| file.take(PREVIEW_MAX_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = read {
+        return PreviewContent::Unreadable(format!("Cannot read file: {}", e));
+    }
+    if bytes.contains(&0) {
+        return PreviewContent::Binary;
+    }
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text,
+        // the cap can split a multi-byte character at the very end, which is not a binary file
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or("")
+        }
+        Err(_) => return PreviewContent::Binary,
+    };
+    PreviewContent::Text(
+        text.lines()
+            .take(PREVIEW_MAX_LINES)
+            .map(|l| l.replace('\t', "    "))
+            .collect(),
+    )
+}
+
+/// The terminal can't be relied on to draw images, so an image is described instead.
+fn image_preview(path: &Path) -> PreviewContent {
+    let size = match path.metadata() {
+        Ok(metadata) => format_size(metadata.len()),
+        Err(e) => return PreviewContent::Unreadable(format!("Cannot read file: {}", e)),
+    };
+    PreviewContent::Image(match image::dimensions(path) {
+        Some((format, width, height)) => {
+            format!("{} image, {} x {}, {}", format, width, height, size)
+        }
+        None => format!("Image, {}", size),
+    })
+}
+
+fn display_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let Some(rest) = path.strip_prefix('~') else {
+        return PathBuf::from(path);
+    };
+    let (user, rest) = rest.split_once(is_separator).unwrap_or((rest, ""));
+    let home = match user {
+        "" => home_dir(),
+        user => home_dir_of(user).map(PathBuf::from),
+    };
+    match home {
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// `$HOME`, or `%USERPROFILE%` on Windows where HOME is usually unset.
+fn home_dir() -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+fn home_dir_of(user: &str) -> Option<String> {
+    let passwd = read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<_> = line.split(':').collect();
+        match fields.first() == Some(&user) {
+            true => fields.get(5).map(|home| home.to_string()),
+            false => None,
+        }
+    })
+}
+
+fn delete_entry(path: &Path) -> io::Result<()> {
+    match symlink_metadata(path)?.is_dir() {
+        true => remove_dir_all(path),
+        false => remove_file(path),
+    }
+}
+
+fn target_in(path: &Path, dir: &Path) -> io::Result<PathBuf> {
+    let Some(name) = path.file_name() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file name"));
+    };
+    let target = dir.join(name);
+    if symlink_metadata(&target).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    Ok(target)
+}
+
+/// Copies `path` into `dir`, removing the partial copy if it fails or is cancelled.
+fn copy_entry(
+    path: &Path,
+    dir: &Path,
+    copier: &mut Copier<impl FnMut(Progress)>,
+) -> io::Result<PathBuf> {
+    let target = target_in(path, dir)?;
+    if path.is_dir() && dir.starts_with(path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot copy a directory into itself",
+        ));
+    }
+    if let Err(e) = copier.copy(path, &target) {
+        if symlink_metadata(&target).is_ok() {
+            delete_entry(&target).ok();
+        }
+        return Err(e);
+    }
+    Ok(target)
+}
+
+fn move_entry(path: &Path, dir: &Path) -> io::Result<PathBuf> {
+    let target = target_in(path, dir)?;
+    move_path(path, &target)?;
+    Ok(target)
+}
+
+/// Renames, falling back to copying and deleting when `to` is on another file system.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_all(from, to)?;
+            delete_entry(from)
+        }
+        result => result,
+    }
+}
+
+/// Copies a file, a symlink as a link, or a directory with everything below it.
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    let file_type = symlink_metadata(from)?.file_type();
+    if file_type.is_symlink() {
+        return copy_symlink(from, to);
+    }
+    if !file_type.is_dir() {
+        return copy(from, to).map(|_| ());
+    }
+    create_dir(to)?;
+    for entry in read_dir(from)? {
+        let entry = entry?;
+        copy_all(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Renames `path` within its directory, refusing names that would move it out of there
+/// or replace a sibling.
+fn rename_entry(path: &Path, name: &str) -> io::Result<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let renamed = target_in(&name_in(dir, name)?, dir)?;
+    rename(path, &renamed)?;
+    Ok(renamed)
+}
+
+/// `name` as an entry of `dir`, refusing names that would point anywhere else.
+fn name_in(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    if name.contains(is_separator) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "file names can't contain a path separator",
+        ));
+    }
+    // an empty name, "." and ".." have no file name
+    if Path::new(name).file_name().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a file name",
+        ));
+    }
+    Ok(dir.join(name))
+}
+
+pub fn read_path_content(path: impl AsRef<Path>, options: ListOptions) -> io::Result<Vec<Entry>> {
+    if cfg!(windows) && path.as_ref() == Path::new(DRIVES) {
+        return Ok(drive_roots());
+    }
+    if let Some((archive, inner)) = split_archive_path(path.as_ref()) {
+        return read_archive_content(&archive, &inner, options);
+    }
+    let entries = read_dir(path)?;
+    let mut entries: Vec<_> = entries
+        .flatten()
+        .filter(|entry| {
+            options.show_hidden || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .flat_map(|entry| {
+            let path = entry.path();
+            let Ok(link_meta) = symlink_metadata(&path) else {
+                return vec![];
+            };
+            let link = match link_meta.file_type().is_symlink() {
+                true => Some(read_link(&path).unwrap_or_default()),
+                false => None,
+            };
+            // fs::metadata follows symlinks, so linked directories are listed as directories
+            let (meta, broken) = match metadata(&path) {
+                Ok(meta) => (meta, false),
+                Err(_) if link.is_some() => (link_meta, true),
+                Err(_) => return vec![],
+            };
+            if (meta.is_file() || meta.is_dir() || broken)
+                && options.shows_type(&path, meta.is_dir())
+            {
+                return vec![Entry {
+                    is_dir: meta.is_dir(),
+                    size: meta.len(),
+                    modified: meta.modified().unwrap_or(UNIX_EPOCH),
+                    link: link.map(|target| Link { target, broken }),
+                    permissions: permissions_of(&meta),
+                    path,
+                }];
+            }
+            vec![]
+        })
+        .collect();
+    sort_contents(&mut entries, options.sort_mode);
+    Ok(entries)
+}
+
+/// The roots of the Windows drives that are present, `A:\` to `Z:\`.
+fn drive_roots() -> Vec<Entry> {
+    ('A'..='Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter)))
+        .filter(|root| root.is_dir())
+        .map(|path| Entry {
+            path,
+            is_dir: true,
+            size: 0,
+            modified: UNIX_EPOCH,
+            link: None,
+            permissions: None,
+        })
+        .collect()
+}
+
+/// Lists one directory of an archive. Directories that only show up as part of a longer
+/// path are listed too.
+fn read_archive_content(
+    archive: &Path,
+    inner: &Path,
+    options: ListOptions,
+) -> io::Result<Vec<Entry>> {
+    let mut entries: Vec<Entry> = vec![];
+    let mut indices: HashMap<PathBuf, usize> = HashMap::new();
+    let mut found = inner.as_os_str().is_empty();
+    for item in read_archive(archive)? {
+        let Ok(rest) = item.path.strip_prefix(inner) else {
+            continue;
+        };
+        let mut components = rest.components();
+        let Some(name) = components.next() else {
+            found = true;
+            continue;
+        };
+        found = true;
+        let nested = components.next().is_some();
+        if !options.show_hidden && name.as_os_str().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = archive.join(inner).join(name);
+        if let Some(&index) = indices.get(&path) {
+            let existing = &mut entries[index];
+            existing.is_dir |= nested;
+            if !nested {
+                existing.size = item.size;
+                existing.modified = item.modified;
+            }
+            continue;
+        }
+        indices.insert(path.clone(), entries.len());
+        entries.push(Entry {
+            is_dir: item.is_dir || nested,
+            size: if nested { 0 } else { item.size },
+            modified: if nested { UNIX_EPOCH } else { item.modified },
+            link: None,
+            permissions: None,
+            path,
+        });
+    }
+    if !found {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "not in the archive",
+        ));
+    }
+    entries.retain(|entry| options.shows_type(&entry.path, entry.is_dir));
+    sort_contents(&mut entries, options.sort_mode);
+    Ok(entries)
+}
+
+fn sort_contents(contents: &mut [Entry], sort_mode: SortMode) {
+    contents.sort_by(|a, b| compare_entries(a, b, sort_mode));
+}
+
+/// Groups directories first, then orders by the sort mode with the largest and newest entries on top.
+fn compare_entries(a: &Entry, b: &Entry, sort_mode: SortMode) -> Ordering {
+    let by_mode = match sort_mode {
+        SortMode::Name => Ordering::Equal,
+        SortMode::Size => b.size.cmp(&a.size),
+        SortMode::ModTime => b.modified.cmp(&a.modified),
+    };
+    b.is_dir
+        .cmp(&a.is_dir)
+        .then(by_mode)
+        .then_with(|| compare_names(&a.path, &b.path))
+}
+
+/// Orders paths by file name, ignoring case, falling back to the exact name so the order is stable.
+fn compare_names(a: &Path, b: &Path) -> Ordering {
+    let a = a.file_name().unwrap_or_default().to_string_lossy();
+    let b = b.file_name().unwrap_or_default().to_string_lossy();
+    a.to_lowercase()
+        .cmp(&b.to_lowercase())
+        .then_with(|| a.cmp(&b))
+}
+
+/// Draws one frame, on any backend so that it can be drawn into a test buffer.
+pub fn draw<B: Backend>(terminal: &mut Terminal<B>, state: &mut State) -> io::Result<()> {
+    // only the layout with the preview in it makes room for an image
+    state.image_area = None;
+    terminal.draw(|frame| {
+        let size = frame.size();
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            let message = Paragraph::new("Terminal too small").wrap(Wrap { trim: true });
+            frame.render_widget(message, size);
+            return;
+        }
+        match state.mode {
+            Mode::Normal
+            | Mode::Help
+            | Mode::Bookmarks
+            | Mode::Search
+            | Mode::Confirm(_)
+            | Mode::Edit(
+                Field::Rename
+                | Field::NewDir
+                | Field::NewFile
+                | Field::Extensions
+                | Field::Filter
+                | Field::Search
+                | Field::Command
+                | Field::Jump,
+            ) => {
+                // the preview makes way before the panes lose their last row
+                let preview_height = match state.preview {
+                    Some(_) => (size.height - 4) * 2 / 5,
+                    None => 0,
+                };
+                let preview_height = match size.height - 6 - preview_height >= MIN_PANE_HEIGHT {
+                    true => preview_height,
+                    false => 0,
+                };
+                let main_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(2)
+                    .constraints(
+                        [
+                            Constraint::Min(MIN_PANE_HEIGHT),
+                            Constraint::Length(preview_height),
+                            Constraint::Length(1),
+                            Constraint::Length(1),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(size);
+
+                let width = main_chunks[0].width;
+                let left_width =
+                    (width * state.split_ratio / 100).clamp(MIN_PANE_WIDTH, width - MIN_PANE_WIDTH);
+                let sides_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .margin(0)
+                    .constraints(
+                        [
+                            Constraint::Length(left_width),
+                            Constraint::Min(MIN_PANE_WIDTH),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(main_chunks[0]);
+
+                let pane_constraints = [
+                    Constraint::Length(2),
+                    Constraint::Min(MIN_PANE_HEIGHT - 3),
+                    Constraint::Length(1),
+                ];
+                let left_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(0)
+                    .constraints(pane_constraints.as_ref())
+                    .split(sides_chunks[0]);
+
+                let right_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(0)
+                    .constraints(pane_constraints.as_ref())
+                    .split(sides_chunks[1]);
+
+                state.left.area = left_chunks[1];
+                state.right.area = right_chunks[1];
+
+                frame.render_widget(
+                    Paragraph::new(breadcrumb(&state.left, left_chunks[0].width)),
+                    left_chunks[0],
+                );
+                frame.render_stateful_widget(
+                    contents_list(state, Side::Left, left_chunks[1].width),
+                    left_chunks[1],
+                    &mut state.left.list_state,
+                );
+                frame.render_widget(Paragraph::new(pane_summary(&state.left)), left_chunks[2]);
+
+                frame.render_widget(
+                    Paragraph::new(breadcrumb(&state.right, right_chunks[0].width)),
+                    right_chunks[0],
+                );
+                frame.render_stateful_widget(
+                    contents_list(state, Side::Right, right_chunks[1].width),
+                    right_chunks[1],
+                    &mut state.right.list_state,
+                );
+                frame.render_widget(Paragraph::new(pane_summary(&state.right)), right_chunks[2]);
+
+                // an overlay would be hidden under the image
+                let overlaid = !matches!(state.mode, Mode::Normal | Mode::Edit(_));
+                state.image_area = match &state.preview {
+                    Some(preview @ Preview {
+                        content: PreviewContent::Image(_),
+                        ..
+                    }) if state.graphics.is_some() && !overlaid => {
+                        // inside the border and below the line describing it
+                        let area = main_chunks[1];
+                        let image = Rect::new(
+                            area.x + 1,
+                            area.y + 2,
+                            area.width.saturating_sub(2),
+                            area.height.saturating_sub(3),
+                        );
+                        Some((preview.path.clone(), image)).filter(|_| image.area() > 0)
+                    }
+                    _ => None,
+                };
+                if let Some(preview) = &state.preview {
+                    // stop once the last line is at the bottom rather than at the top
+                    let rows = main_chunks[1].height.saturating_sub(2) as usize;
+                    let end = preview.line_count().saturating_sub(rows) as u16;
+                    state.preview_scroll = state.preview_scroll.min(end);
+                    frame.render_widget(
+                        preview_paragraph(preview, &state.theme).scroll((state.preview_scroll, 0)),
+                        main_chunks[1],
+                    );
+                }
+
+                let status = match state.mode {
+                    Mode::Confirm(Confirmation::Delete) => {
+                        let targets = state.active_pane().targets();
+                        let name = match targets.as_slice() {
+                            [path] => path.display().to_string(),
+                            targets => format!("{} marked entries", targets.len()),
+                        };
+                        let verb = match state.permanent_delete {
+                            true => "Delete",
+                            false => "Trash",
+                        };
+                        Paragraph::new(format!("{} {}? (y/n)", verb, name))
+                            .style(Style::default().fg(state.theme.prompt))
+                    }
+                    Mode::Confirm(Confirmation::Quit) => Paragraph::new(format!(
+                        "{} in progress — quit anyway? (y/n)",
+                        state.pending_tasks().join(", ")
+                    ))
+                    .style(Style::default().fg(state.theme.prompt)),
+                    Mode::Edit(field) => {
+                        let prefix = match field {
+                            Field::Command => ":".to_string(),
+                            field => format!("{}: ", field.prompt()),
+                        };
+                        set_input_cursor(frame, state, main_chunks[2], prefix.chars().count());
+                        Paragraph::new(format!("{}{}", prefix, state.input))
+                    }
+                    _ => match &state.copy_job {
+                        Some(job) => Paragraph::new(copy_status(job)),
+                        None => Paragraph::new(state.status_message.clone().unwrap_or_default()),
+                    },
+                };
+                frame.render_widget(status, main_chunks[2]);
+                frame.render_widget(status_bar(state), main_chunks[3]);
+
+                if let Mode::Help = state.mode {
+                    let rows = help_rows(&state.keymap);
+                    let height = rows.len() as u16 + 2;
+                    let key_width = rows.iter().map(|(keys, _)| keys.width()).max();
+                    let action_width = rows.iter().map(|(_, action)| action.width()).max();
+                    let width = (key_width.unwrap_or(0) + action_width.unwrap_or(0) + 5) as u16;
+                    let area = centered_rect(width.max(56), height, size);
+                    let help = help_paragraph(rows, &state.theme);
+                    frame.render_widget(Clear, area);
+                    frame.render_widget(help, area);
+                }
+
+                if let Mode::Bookmarks = state.mode {
+                    let height = state.bookmarks.len().max(1) as u16 + 2;
+                    let area = centered_rect(size.width * 3 / 4, height, size);
+                    frame.render_widget(Clear, area);
+                    frame.render_stateful_widget(
+                        bookmarks_list(&state.bookmarks, &state.theme),
+                        area,
+                        &mut state.bookmark_list_state,
+                    );
+                }
+
+                if let Mode::Confirm(Confirmation::Delete) = state.mode {
+                    let targets = state.active_pane().targets();
+                    if targets.len() > 1 {
+                        let width = (size.width * 3 / 4).min(72);
+                        let summary = delete_summary(
+                            &targets,
+                            state.permanent_delete,
+                            width.saturating_sub(2) as usize,
+                            &state.theme,
+                        );
+                        let height = summary.len() as u16 + 2;
+                        let area = centered_rect(width, height, size);
+                        frame.render_widget(Clear, area);
+                        frame.render_widget(summary, area);
+                    }
+                }
+
+                if let (Mode::Search, Some(search)) = (state.mode, state.search.as_mut()) {
+                    let rows = search.results.as_ref().map_or(1, |r| r.len().max(1));
+                    let height = rows.min(size.height as usize) as u16 + 2;
+                    let area = centered_rect(size.width * 3 / 4, height, size);
+                    frame.render_widget(Clear, area);
+                    frame.render_stateful_widget(
+                        search_list(search, &state.theme),
+                        area,
+                        &mut search.list_state,
+                    );
+                }
+            }
+            Mode::Edit(field) => {
+                let side = field.side().unwrap_or(state.active);
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(2)
+                    .constraints(
+                        [
+                            Constraint::Length(4),
+                            Constraint::Min(5),
+                            Constraint::Length(1),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(size);
+
+                // the last results may not match the input anymore, so they wait
+                let searching = state.fzf_searching();
+                let list_items: Vec<_> = match searching {
+                    true => vec![ListItem::new("Searching…")
+                        .style(Style::default().add_modifier(Modifier::DIM))],
+                    false => state
+                        .pane(side)
+                        .suggestions
+                        .items
+                        .iter()
+                        .map(|i| {
+                            ListItem::new(Line::from(vec![Span::styled(i.clone(), Style::default())]))
+                        })
+                        .collect(),
+                };
+
+                let lists_ui = List::new(list_items)
+                    .block(
+                        Block::default()
+                            .title(format!(
+                                "List (depth {}, {}, {}, Ctrl+Up/Down, Ctrl+S and Ctrl+F to change)",
+                                state.fzf_max_depth,
+                                state.fzf_case.label(),
+                                files_label(state.fzf_files)
+                            ))
+                            .borders(Borders::ALL),
+                    )
+                    .style(Style::default().fg(state.theme.file))
+                    .highlight_style(state.theme.selected_style())
+                    .highlight_symbol(">>");
+
+                let paragraph = Paragraph::new(state.input.clone())
+                    .block(Block::default().title("path").borders(Borders::ALL));
+
+                frame.render_widget(paragraph, chunks[0]);
+                // inside the border
+                let inner = Rect {
+                    x: chunks[0].x + 1,
+                    y: chunks[0].y + 1,
+                    width: chunks[0].width.saturating_sub(2),
+                    height: 1,
+                };
+                set_input_cursor(frame, state, inner, 0);
+                let mut placeholder_state = ListState::default();
+                let list_state = match searching {
+                    true => &mut placeholder_state,
+                    false => &mut state.pane_mut(side).suggestions.list_state,
+                };
+                frame.render_stateful_widget(lists_ui, chunks[1], list_state);
+                frame.render_widget(
+                    Paragraph::new(state.status_message.clone().unwrap_or_default()),
+                    chunks[2],
+                );
+            }
+        }
+    })?;
+    Ok(())
+}
+
+/// Puts the terminal cursor at `state.input_cursor` in input drawn on the first row of
+/// `area` after `offset` columns of prompt.
+fn set_input_cursor<B: Backend>(frame: &mut Frame<B>, state: &State, area: Rect, offset: usize) {
+    let column = offset + state.input[..state.input_cursor].width();
+    let column = (column as u16).min(area.width.saturating_sub(1));
+    frame.set_cursor(area.x + column, area.y);
+}
+
+/// The pane header: the path with the current directory in bold, cut from the left
+/// when it doesn't fit so the deepest components stay visible.
+fn breadcrumb(pane: &Pane, width: u16) -> Line<'static> {
+    let mut suffix = match pane.filter.is_empty() {
+        true => String::new(),
+        false => format!("  /{}", pane.filter),
+    };
+    if pane.loading {
+        suffix.push_str("  Loading…");
+    }
+    let width = (width as usize).saturating_sub(suffix.chars().count());
+    let path = truncate_left(&pane.path, width);
+    let split = path
+        .trim_end_matches(is_separator)
+        .rfind(is_separator)
+        .map_or(0, |i| i + 1);
+    let (parents, current) = path.split_at(split);
+    Line::from(vec![
+        Span::raw(parents.to_string()),
+        Span::styled(
+            current.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(suffix),
+    ])
+}
+
+/// The footer under a pane, e.g. "3 dirs, 12 files, 4.5M", for the entries listed.
+fn pane_summary(pane: &Pane) -> String {
+    let entries = pane.visible();
+    let (dirs, files): (Vec<&Entry>, Vec<&Entry>) = entries.iter().partition(|entry| entry.is_dir);
+    let plural = |count: usize, word: &str| match count {
+        1 => format!("1 {}", word),
+        count => format!("{} {}s", count, word),
+    };
+    format!(
+        " {}, {}, {}",
+        plural(dirs.len(), "dir"),
+        plural(files.len(), "file"),
+        format_size(files.iter().map(|entry| entry.size).sum())
+    )
+}
+
+fn truncate_left(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let tail: String = take_width(text.chars().rev(), width - 1);
+    let tail: String = tail.chars().rev().collect();
+    // start at a separator when there is one so no directory name is cut in half
+    let tail = match tail.find(is_separator) {
+        Some(index) if index + 1 < tail.len() => tail[index..].to_string(),
+        _ => tail,
+    };
+    format!("…{}", tail)
+}
+
+fn copy_status(job: &CopyJob) -> String {
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let frame = (job.started.elapsed().as_millis() / 100) as usize % SPINNER.len();
+    let progress = job.progress;
+    let files = match progress.files {
+        1 => "file",
+        _ => "files",
+    };
+    format!(
+        "{} Copying: {} {}, {} (Esc cancels)",
+        SPINNER[frame],
+        progress.files,
+        files,
+        format_size(progress.bytes)
+    )
+}
+
+/// The flags that change what the panes show, so they don't have to be remembered.
+fn status_bar(state: &State) -> Paragraph<'static> {
+    let value = Style::default().fg(state.theme.prompt);
+    let side = match state.active {
+        Side::Left => "left",
+        Side::Right => "right",
+    };
+    let hidden = match state.show_hidden {
+        true => "shown",
+        false => "hidden",
+    };
+    let mut spans = vec![
+        Span::raw("pane "),
+        Span::styled(side, value),
+        Span::raw("  sort "),
+        Span::styled(state.sort_mode.label(), value),
+        Span::raw("  dotfiles "),
+        Span::styled(hidden, value),
+    ];
+    if state.filter_extensions {
+        spans.push(Span::raw("  types "));
+        spans.push(Span::styled(state.extensions.join(","), value));
+    }
+    if state.follow_symlinks {
+        spans.push(Span::raw("  symlinks "));
+        spans.push(Span::styled("followed", value));
+    }
+    if state.pick {
+        spans.push(Span::raw("  Enter "));
+        spans.push(Span::styled("picks this directory", value));
+    }
+    if state.compare {
+        spans.push(Span::raw("  compare "));
+        spans.push(Span::styled("+ only here, ≠ differs", value));
+    }
+    let filter = &state.active_pane().filter;
+    if !filter.is_empty() {
+        spans.push(Span::raw("  filter "));
+        spans.push(Span::styled(filter.clone(), value));
+    }
+    Paragraph::new(Line::from(spans))
+}
+
+fn help_paragraph(rows: Vec<(String, &'static str)>, theme: &Theme) -> Paragraph<'static> {
+    let key_width = rows.iter().map(|(keys, _)| keys.width()).max().unwrap_or(0);
+    let lines: Vec<_> = rows
+        .into_iter()
+        .map(|(key, action)| {
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:<width$}  ", key, width = key_width),
+                    Style::default().fg(theme.prompt),
+                ),
+                Span::raw(action),
+            ])
+        })
+        .collect();
+    Paragraph::new(lines).block(
+        Block::default()
+            .title("Keys")
+            .borders(Borders::ALL)
+            .border_style(theme.border_style(true)),
+    )
+}
+
+fn search_list(search: &Search, theme: &Theme) -> List<'static> {
+    let (title, items) = match &search.results {
+        None => ("Searching…".to_string(), vec![]),
+        Some(results) => (
+            format!("\"{}\" ({} found)", search.query, results.len()),
+            results
+                .iter()
+                .map(|path| {
+                    let relative = path.strip_prefix(&search.root).unwrap_or(path);
+                    ListItem::new(relative.display().to_string())
+                })
+                .collect(),
+        ),
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.border_style(true)),
+        )
+        .style(Style::default().fg(theme.file))
+        .highlight_style(theme.selected_style())
+        .highlight_symbol(">>")
+}
+
+fn bookmarks_list<'a>(bookmarks: &'a [String], theme: &Theme) -> List<'a> {
+    let items: Vec<_> = match bookmarks.is_empty() {
+        true => vec![ListItem::new("No bookmarks yet, press b to add one")],
+        false => bookmarks
+            .iter()
+            .map(|b| ListItem::new(b.as_str()))
+            .collect(),
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .title("Bookmarks")
+                .borders(Borders::ALL)
+                .border_style(theme.border_style(true)),
+        )
+        .style(Style::default().fg(theme.directory))
+        .highlight_style(theme.selected_style())
+        .highlight_symbol(">>")
+}
+
+/// The names about to be deleted, cut to `width` columns, so a stray mark is seen before `y`.
+fn delete_summary<'a>(
+    targets: &[PathBuf],
+    permanent: bool,
+    width: usize,
+    theme: &Theme,
+) -> List<'a> {
+    const MAX_ROWS: usize = 10;
+    let shown = match targets.len() > MAX_ROWS {
+        true => MAX_ROWS - 1,
+        false => targets.len(),
+    };
+    let mut items: Vec<_> = targets[..shown]
+        .iter()
+        .map(|path| {
+            let mut name = display_name(path);
+            if path.is_dir() {
+                name.push(MAIN_SEPARATOR);
+            }
+            ListItem::new(truncate_right(&name, width))
+        })
+        .collect();
+    if shown < targets.len() {
+        items.push(ListItem::new(format!(
+            "…and {} more",
+            targets.len() - shown
+        )));
+    }
+    let verb = match permanent {
+        true => "Delete",
+        false => "Trash",
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .title(format!("{} {} entries? (y/n)", verb, targets.len()))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.prompt)),
+        )
+        .style(Style::default().fg(theme.directory))
+}
+
+/// `text` cut to `width` columns, with an ellipsis where it was cut.
+fn truncate_right(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    format!("{}…", take_width(text.chars(), width.saturating_sub(1)))
+}
+
+/// As many of `chars` as fit in `width` columns.
+fn take_width(chars: impl Iterator<Item = char>, width: usize) -> String {
+    let mut used = 0;
+    chars
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
+
+/// `text` cut or padded with spaces to exactly `width` columns. Emoji and CJK take two
+/// columns each, so padding by chars would push the columns after them out of line.
+fn fit_width(text: &str, width: usize) -> String {
+    let fitted = take_width(text.chars(), width);
+    // a sequence like ❤️ can measure wider as a whole than its characters did one by one
+    let padding = width.saturating_sub(fitted.width());
+    fitted + &" ".repeat(padding)
+}
+
+fn files_label(files: bool) -> &'static str {
+    match files {
+        true => "directories and files",
+        false => "directories only",
+    }
+}
+
+/// A `width` x `height` rect in the middle of `area`, shrunk to fit if it is too small.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn preview_paragraph<'a>(preview: &'a Preview, theme: &Theme) -> Paragraph<'a> {
+    let lines: Vec<Line> = match &preview.content {
+        PreviewContent::Text(lines) => lines.iter().map(|l| Line::from(l.as_str())).collect(),
+        PreviewContent::Code(lines) => lines.clone(),
+        PreviewContent::Binary => vec![Line::from("Binary file")],
+        PreviewContent::Unreadable(text) | PreviewContent::Image(text) => {
+            vec![Line::from(text.as_str())]
+        }
+    };
+    Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(display_name(&preview.path))
+                .borders(Borders::ALL)
+                .border_style(theme.border_style(false)),
+        )
+        .style(Style::default().fg(theme.file))
+}
+
+/// The rows for the entries of `side`'s pane, fitted to `width`, the width of the pane
+/// including its borders. Columns that are turned off in `state` take no room.
+fn contents_list(state: &State, side: Side, width: u16) -> List<'static> {
+    let pane = state.pane(side);
+    let theme = &state.theme;
+    let icons = state.use_icons;
+    let owners = state.show_permissions.then_some(&state.owners);
+    let differences = match (&pane.contents, &state.pane(side.other()).contents) {
+        (Some(contents), Some(other)) if state.compare => compare(contents, other),
+        _ => HashMap::new(),
+    };
+    let contents = pane.visible();
+    let owner_columns: Vec<Option<(String, String, String)>> = contents
+        .iter()
+        .map(|entry| {
+            let (owners, permissions) = (owners?, entry.permissions?);
+            Some((
+                mode_string(permissions.mode),
+                owners.user(permissions.uid),
+                owners.group(permissions.gid),
+            ))
+        })
+        .collect();
+    let column_width = |column: fn(&(String, String, String)) -> &String| {
+        owner_columns
+            .iter()
+            .flatten()
+            .map(|columns| column(columns).width())
+            .max()
+            .unwrap_or(0)
+    };
+    let user_width = column_width(|columns| &columns.1);
+    let group_width = column_width(|columns| &columns.2);
+    let owner_width = match user_width {
+        0 => 0,
+        _ => 1 + 9 + 1 + user_width + 1 + group_width,
+    };
+
+    let now = SystemTime::now();
+    let times: Vec<String> = contents
+        .iter()
+        .map(|entry| format_time(entry.modified, now, state.time_format))
+        .collect();
+
+    // borders, the highlight symbol and the mark, plus the icon and its space and the compare
+    // marker
+    let icon_width = if icons { 2 } else { 0 };
+    let compare_width = if state.compare { 1 } else { 0 };
+    let available = (width as usize).saturating_sub(2 + 2 + 1 + icon_width + compare_width);
+    // when the pane is narrow the modified column goes first, then the permissions, then the
+    // size, each with the space before it
+    let time_width = times.iter().map(|time| time.len()).max().unwrap_or(0);
+    let columns = [
+        1 + SIZE_WIDTH,
+        owner_width,
+        time_width + usize::from(time_width > 0),
+    ];
+    let kept = (0..=columns.len())
+        .rev()
+        .find(|&kept| {
+            let used: usize = columns[..kept].iter().sum();
+            available.saturating_sub(used) >= MIN_NAME_WIDTH
+        })
+        .unwrap_or(0);
+    let name_width = available.saturating_sub(columns[..kept].iter().sum());
+    let show_size = kept >= 1;
+    let owner_width = if kept >= 2 { owner_width } else { 0 };
+    let time_width = if kept >= 3 { time_width } else { 0 };
+    let list_items: Vec<_> = contents
+        .iter()
+        .zip(&owner_columns)
+        .zip(&times)
+        .map(|((entry, owner_column), time)| {
+            let (mut name, mut size, mut style) = match entry.is_dir {
+                true => (
+                    format!("{}/", display_name(&entry.path)),
+                    match pane.dir_sizes.get(&entry.path) {
+                        Some(size) => format_size(*size),
+                        None => "<DIR>".to_string(),
+                    },
+                    Style::default().fg(theme.directory),
+                ),
+                false => (
+                    display_name(&entry.path),
+                    format_size(entry.size),
+                    Style::default().fg(theme.file),
+                ),
+            };
+            if let Some(link) = &entry.link {
+                name = format!("{} -> {}", name, link.target.display());
+                style = Style::default().fg(theme.symlink);
+                if link.broken {
+                    size = "broken".to_string();
+                    style = Style::default().fg(theme.broken_link);
+                }
+            }
+            let mark = if pane.marked.contains(&entry.path) {
+                "*"
+            } else {
+                " "
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(mark, Style::default().fg(theme.prompt)),
+                Span::styled(
+                    match (state.compare, differences.get(&entry.path)) {
+                        (true, Some(difference)) => difference.marker().to_string(),
+                        (true, None) => " ".to_string(),
+                        (false, _) => String::new(),
+                    },
+                    Style::default().fg(theme.prompt),
+                ),
+                Span::styled(
+                    match icons {
+                        true => format!("{} ", icon_for(entry)),
+                        false => String::new(),
+                    },
+                    style,
+                ),
+                Span::styled(fit_width(&name, name_width), style),
+                Span::raw(match owner_column {
+                    _ if owner_width == 0 => String::new(),
+                    Some((mode, user, group)) => {
+                        let (user, group) =
+                            (fit_width(user, user_width), fit_width(group, group_width));
+                        format!(" {} {} {}", mode, user, group)
+                    }
+                    None => " ".repeat(owner_width),
+                }),
+                Span::raw(match time_width {
+                    0 => String::new(),
+                    _ => format!(" {:>width$}", time, width = time_width),
+                }),
+                Span::styled(
+                    match show_size {
+                        true => format!(" {:>width$}", size, width = SIZE_WIDTH),
+                        false => String::new(),
+                    },
+                    style,
+                ),
+            ]))
+        })
+        .collect();
+
+    let title = match (pane.list_state.selected(), contents.len()) {
+        (_, 0) => "(empty)".to_string(),
+        (Some(index), len) => format!("({}/{})", index + 1, len),
+        (None, len) => format!("({})", len),
+    };
+    let title = format!("{} {}", display_name(Path::new(&pane.path)), title);
+
+    List::new(list_items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(theme.border_style(state.active == side)),
+        )
+        .style(Style::default().fg(theme.file))
+        .highlight_style(theme.selected_style())
+        .highlight_symbol(">>")
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{}{}", bytes, UNITS[0]),
+        _ => format!("{:.1}{}", size, UNITS[unit]),
+    }
+}
+
+fn missing_binary(e: io::Error, binary: &str) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found in PATH", binary),
+        ),
+        _ => e,
+    }
+}
+
+pub async fn run_fzf_query(
+    query: &str,
+    options: SuggestionOptions,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    // a leading '-' would make find read the query as an expression rather than a path
+    let root = match query.starts_with('-') {
+        true => format!("./{}", query),
+        false => query.to_string(),
+    };
+    let mut find = Command::new("find")
+        .arg(&root)
+        .arg("-maxdepth")
+        .arg(options.max_depth.to_string())
+        .args(match options.files {
+            true => &["(", "-type", "d", "-o", "-type", "f", ")", "-print"][..],
+            false => &["-type", "d", "-print"],
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| missing_binary(e, "find"))?;
+    let find_stdout: Stdio = find
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("find has no stdout"))?
+        .try_into()?;
+    let fzf_output = Command::new("fzf")
+        .arg(format!("--filter={}", query))
+        // the query is a path, so spaces, quotes and ! in it are matched as they are
+        // instead of being read as fzf's search syntax
+        .arg("--no-extended")
+        .args(options.case.fzf_flag())
+        .stdin(find_stdout)
+        .output()
+        .await
+        .map_err(|e| missing_binary(e, "fzf"))?;
+    find.wait().await?;
+
+    // fzf exits with 1 when nothing matched, which is not an error for suggestions
+    match fzf_output.status.success() || fzf_output.status.code() == Some(1) {
+        true => {
+            let output_str = String::from_utf8_lossy(&fzf_output.stdout);
+            Ok(output_str
+                .to_string()
+                .lines()
+                .map(|l| l.to_string())
+                .collect())
+        }
+        false => Err(Box::new(std::io::Error::other(format!(
+            "Failed to run fzf query: {}",
+            query
+        )))),
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashMap, fs::read_to_string, io, path::Path};
+
+/// Unix mode bits and owner ids. Not available on other platforms.
+#[derive(Debug, Clone, Copy)]
+pub struct Permissions {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+#[cfg(unix)]
+pub fn permissions_of(meta: &std::fs::Metadata) -> Option<Permissions> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    Some(Permissions {
+        mode: meta.permissions().mode(),
+        uid: meta.uid(),
+        gid: meta.gid(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn permissions_of(_meta: &std::fs::Metadata) -> Option<Permissions> {
+    None
+}
+
+/// Sets the permission bits of `path`, following symlinks like chmod does.
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "permissions can only be changed on Unix",
+    ))
+}
+
+/// Formats the permission bits like `ls -l` does, e.g. `rwxr-xr-x`.
+pub fn mode_string(mode: u32) -> String {
+    let mut text = String::new();
+    for shift in [6, 3, 0] {
+        let bits = mode >> shift;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    text
+}
+
+/// User and group names from `/etc/passwd` and `/etc/group`, read once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct Owners {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Owners {
+    pub fn load() -> Owners {
+        Owners {
+            users: read_id_names("/etc/passwd"),
+            groups: read_id_names("/etc/group"),
+        }
+    }
+
+    /// The user name, or the bare uid when it isn't in `/etc/passwd`.
+    pub fn user(&self, uid: u32) -> String {
+        self.users.get(&uid).cloned().unwrap_or(uid.to_string())
+    }
+
+    pub fn group(&self, gid: u32) -> String {
+        self.groups.get(&gid).cloned().unwrap_or(gid.to_string())
+    }
+}
+
+/// Both files are `name:password:id:...` lines.
+fn read_id_names(path: &str) -> HashMap<u32, String> {
+    let Ok(text) = read_to_string(path) else {
+        return HashMap::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
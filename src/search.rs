@@ -0,0 +1,83 @@
+use std::{
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+const MAX_DEPTH: usize = 8;
+const MAX_RESULTS: usize = 1000;
+
+/// Finds files and directories under `root` whose name contains `query`, ignoring case.
+/// `.git` and anything matched by a `.gitignore` on the way down is skipped.
+pub fn search_names(query: &str, root: &Path) -> Vec<PathBuf> {
+    let mut results = vec![];
+    walk(&query.to_lowercase(), root, 0, &[], &mut results);
+    results
+}
+
+fn walk(query: &str, dir: &Path, depth: usize, ignored: &[String], results: &mut Vec<PathBuf>) {
+    let mut ignored = ignored.to_vec();
+    ignored.extend(gitignore_patterns(dir));
+
+    let Ok(entries) = read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        if results.len() >= MAX_RESULTS {
+            return;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == ".git" || ignored.iter().any(|pattern| glob_match(pattern, &name)) {
+            continue;
+        }
+        if name.to_lowercase().contains(query) {
+            results.push(entry.path());
+        }
+        // symlinks are not followed so link cycles can't recurse forever
+        if depth < MAX_DEPTH && entry.file_type().is_ok_and(|t| t.is_dir()) {
+            walk(query, &entry.path(), depth + 1, &ignored, results);
+        }
+    }
+}
+
+/// Reads the name patterns of a `.gitignore`. Negations and patterns with a path in them
+/// aren't supported and are left out.
+fn gitignore_patterns(dir: &Path) -> Vec<String> {
+    let Ok(text) = read_to_string(dir.join(".gitignore")) else {
+        return vec![];
+    };
+    text.lines()
+        .map(|line| line.trim().trim_end_matches('/'))
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+        .map(|line| line.trim_start_matches('/'))
+        .filter(|line| !line.contains('/'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Matches `name` against a pattern where `*` stands for any run of characters and `?` for one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // where to resume after the last `*` if the rest fails to match
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
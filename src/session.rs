@@ -0,0 +1,68 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    io,
+    path::PathBuf,
+};
+
+use crate::{
+    config::{config_dir, parse_toml, quote, Value},
+    Side,
+};
+
+/// Where the browser was when it last quit, kept in `session.toml` next to the config.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub left_path: String,
+    pub right_path: String,
+    pub active: Side,
+}
+
+fn session_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("session.toml"))
+}
+
+impl Session {
+    /// Returns None when there is no saved session or it can't be parsed.
+    pub fn load() -> Option<Session> {
+        let (tables, _) = parse_toml(&read_to_string(session_file()?).ok()?);
+        let root = tables.get("")?;
+        let path = |key: &str| match root.get(key) {
+            Some(Value::String(path)) => Some(path.clone()),
+            _ => None,
+        };
+        let active = match root.get("active") {
+            Some(Value::String(side)) if side == "right" => Side::Right,
+            _ => Side::Left,
+        };
+        Some(Session {
+            left_path: path("left_path")?,
+            right_path: path("right_path")?,
+            active,
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = session_file() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory",
+            ));
+        };
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let active = match self.active {
+            Side::Left => "left",
+            Side::Right => "right",
+        };
+        write(
+            path,
+            format!(
+                "left_path = {}\nright_path = {}\nactive = {}\n",
+                quote(&self.left_path),
+                quote(&self.right_path),
+                quote(active)
+            ),
+        )
+    }
+}
@@ -0,0 +1,125 @@
+use ratatui::style::{Color, Modifier, Style};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub border: Color,
+    pub active_border: Color,
+    pub selected_fg: Color,
+    pub selected_bg: Color,
+    pub directory: Color,
+    pub file: Color,
+    pub symlink: Color,
+    pub broken_link: Color,
+    pub prompt: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::builtin("dark").unwrap()
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme {
+                border: Color::Reset,
+                active_border: Color::Yellow,
+                selected_fg: Color::Reset,
+                selected_bg: Color::DarkGray,
+                directory: Color::Blue,
+                file: Color::White,
+                symlink: Color::Cyan,
+                broken_link: Color::Red,
+                prompt: Color::Yellow,
+            }),
+            "light" => Some(Theme {
+                border: Color::Gray,
+                active_border: Color::Magenta,
+                selected_fg: Color::Black,
+                selected_bg: Color::LightYellow,
+                directory: Color::Blue,
+                file: Color::Black,
+                symlink: Color::Cyan,
+                broken_link: Color::Red,
+                prompt: Color::Magenta,
+            }),
+            "mono" => Some(Theme {
+                border: Color::Reset,
+                active_border: Color::Reset,
+                selected_fg: Color::Reset,
+                selected_bg: Color::Reset,
+                directory: Color::Reset,
+                file: Color::Reset,
+                symlink: Color::Reset,
+                broken_link: Color::Reset,
+                prompt: Color::Reset,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Sets one color by its config name, returning false for unknown names.
+    pub fn set(&mut self, name: &str, color: Color) -> bool {
+        let slot = match name {
+            "border" => &mut self.border,
+            "active_border" => &mut self.active_border,
+            "selected_fg" => &mut self.selected_fg,
+            "selected_bg" => &mut self.selected_bg,
+            "directory" => &mut self.directory,
+            "file" => &mut self.file,
+            "symlink" => &mut self.symlink,
+            "broken_link" => &mut self.broken_link,
+            "prompt" => &mut self.prompt,
+            _ => return false,
+        };
+        *slot = color;
+        true
+    }
+
+    pub fn border_style(&self, active: bool) -> Style {
+        match active {
+            true => Style::default().fg(self.active_border),
+            false => Style::default().fg(self.border),
+        }
+    }
+
+    pub fn selected_style(&self) -> Style {
+        Style::default()
+            .fg(self.selected_fg)
+            .bg(self.selected_bg)
+            .add_modifier(Modifier::ITALIC)
+    }
+}
+
+/// Accepts ratatui's color names (`light_blue`, `dark_gray`, ...) and `#rrggbb`.
+pub fn parse_color(text: &str) -> Option<Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    let color = match text.to_lowercase().replace(['-', ' '], "_").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark_gray" | "dark_grey" => Color::DarkGray,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs::{create_dir, read_dir, read_link, set_permissions, symlink_metadata, File},
+    io::{self, Read, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// Progress goes out at most this often so a fast copy doesn't flood the channel.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How much of a copy is done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Copies in chunks, reporting progress as it goes and stopping once `cancel` is set.
+pub struct Copier<'a, F: FnMut(Progress)> {
+    cancel: &'a AtomicBool,
+    report: F,
+    progress: Progress,
+    reported_at: Instant,
+    buffer: Vec<u8>,
+}
+
+impl<'a, F: FnMut(Progress)> Copier<'a, F> {
+    pub fn new(cancel: &'a AtomicBool, report: F) -> Self {
+        Copier {
+            cancel,
+            report,
+            progress: Progress::default(),
+            reported_at: Instant::now(),
+            buffer: vec![0; CHUNK_SIZE],
+        }
+    }
+
+    /// Copies a file, or a directory with everything below it, to `to`, which mustn't
+    /// exist yet. Symlinks are copied as links, so a link to a directory doesn't copy the
+    /// directory and a link cycle ends. Whatever was copied before an error or a cancel
+    /// is left in place.
+    pub fn copy(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let file_type = symlink_metadata(from)?.file_type();
+        if file_type.is_symlink() {
+            copy_symlink(from, to)?;
+            self.progress.files += 1;
+            self.report();
+            return Ok(());
+        }
+        if !file_type.is_dir() {
+            return self.copy_file(from, to);
+        }
+        create_dir(to)?;
+        for entry in read_dir(from)? {
+            let entry = entry?;
+            self.copy(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut source = File::open(from)?;
+        let mut target = File::create_new(to)?;
+        loop {
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err(io::Error::other("cancelled"));
+            }
+            let read = match source.read(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            target.write_all(&self.buffer[..read])?;
+            self.progress.bytes += read as u64;
+            self.report();
+        }
+        // fs::copy keeps the permissions too
+        set_permissions(to, source.metadata()?.permissions())?;
+        self.progress.files += 1;
+        self.report();
+        Ok(())
+    }
+
+    fn report(&mut self) {
+        if self.reported_at.elapsed() >= REPORT_INTERVAL {
+            self.reported_at = Instant::now();
+            (self.report)(self.progress);
+        }
+    }
+}
+
+/// Makes `to` a symlink pointing where the symlink `from` does.
+pub fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = read_link(from)?;
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, to);
+    // Windows has separate links for directories and files
+    #[cfg(windows)]
+    return match from.is_dir() {
+        true => std::os::windows::fs::symlink_dir(target, to),
+        false => std::os::windows::fs::symlink_file(target, to),
+    };
+}
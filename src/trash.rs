@@ -0,0 +1,91 @@
+use std::{
+    env,
+    fs::{create_dir_all, symlink_metadata, write},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{dates::format_timestamp, delete_entry, home_dir, move_path};
+
+/// The home trash from the freedesktop.org trash spec, `$XDG_DATA_HOME/Trash`.
+fn trash_dir() -> Option<PathBuf> {
+    let data = match env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".local/share"),
+    };
+    Some(data.join("Trash"))
+}
+
+/// Moves `path` into the trash with a `.trashinfo` recording where it came from, so file
+/// managers can restore it. Returns where the entry now lives.
+pub fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+    let Some(trash) = trash_dir() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no home directory"));
+    };
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    create_dir_all(&files)?;
+    create_dir_all(&info)?;
+
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file name"));
+    };
+    // only the parent is resolved so that a symlink is trashed rather than its target
+    let original = parent.canonicalize()?.join(name);
+    let name = name.to_string_lossy().to_string();
+    // the spec wants a name that isn't taken in files/ or info/ yet
+    let mut trashed_name = name.clone();
+    let mut number = 1;
+    while symlink_metadata(files.join(&trashed_name)).is_ok()
+        || symlink_metadata(info.join(format!("{}.trashinfo", trashed_name))).is_ok()
+    {
+        number += 1;
+        trashed_name = format!("{} {}", name, number);
+    }
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let info_file = info.join(format!("{}.trashinfo", trashed_name));
+    write(
+        &info_file,
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&original.to_string_lossy()),
+            format_timestamp(seconds)
+        ),
+    )?;
+
+    let trashed = files.join(&trashed_name);
+    if let Err(e) = move_path(&original, &trashed) {
+        delete_entry(&info_file).ok();
+        return Err(e);
+    }
+    Ok(trashed)
+}
+
+/// Puts an entry from `move_to_trash` back at `original` and drops its `.trashinfo`.
+pub fn restore_from_trash(trashed: &Path, original: &Path) -> io::Result<()> {
+    move_path(trashed, original)?;
+    if let (Some(trash), Some(name)) = (trash_dir(), trashed.file_name()) {
+        let info_file = trash
+            .join("info")
+            .join(format!("{}.trashinfo", name.to_string_lossy()));
+        delete_entry(&info_file).ok();
+    }
+    Ok(())
+}
+
+/// Escapes everything but unreserved characters and `/`, as the `Path` key requires.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,31 @@
+use std::{
+    fs::{read_dir, symlink_metadata},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The total size of the files below `dir`, like `du --apparent-size`. Symlinks aren't
+/// followed and unreadable entries are skipped. Gives None once `cancel` is set.
+pub fn dir_size(dir: &Path, cancel: &AtomicBool) -> Option<u64> {
+    let mut total = 0;
+    let mut pending: Vec<PathBuf> = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let Ok(entries) = read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = symlink_metadata(entry.path()) else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += meta.len();
+            }
+        }
+    }
+    Some(total)
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs::metadata,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reports directories whose entries changed. Creating, deleting or renaming an entry
+/// updates the directory's mtime, so polling that is enough without the notify crate.
+pub struct Watcher {
+    dirs: Arc<Mutex<Vec<(PathBuf, SystemTime)>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn new(sender: UnboundedSender<PathBuf>) -> Watcher {
+        let dirs = Arc::new(Mutex::new(vec![]));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (dirs, stop) = (dirs.clone(), stop.clone());
+            thread::spawn(move || poll(dirs, stop, sender))
+        };
+        Watcher {
+            dirs,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Replaces the watched directories, each with the mtime it had when it was listed.
+    /// A directory is reported on every poll until it is listed again with its new mtime,
+    /// so a change made while it was being read isn't missed. Cheap to call when nothing
+    /// changed.
+    pub fn watch(&self, dirs: Vec<(PathBuf, SystemTime)>) {
+        if let Ok(mut watched) = self.dirs.lock() {
+            if *watched != dirs {
+                *watched = dirs;
+            }
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            thread.join().ok();
+        }
+    }
+}
+
+fn poll(
+    dirs: Arc<Mutex<Vec<(PathBuf, SystemTime)>>>,
+    stop: Arc<AtomicBool>,
+    sender: UnboundedSender<PathBuf>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        let dirs = dirs.lock().map(|dirs| dirs.clone()).unwrap_or_default();
+        for (dir, listed) in dirs {
+            // directories that are gone have no mtime to compare
+            let Ok(modified) = metadata(&dir).and_then(|meta| meta.modified()) else {
+                continue;
+            };
+            if modified != listed && sender.send(dir).is_err() {
+                return;
+            }
+        }
+        thread::park_timeout(POLL_INTERVAL);
+    }
+}
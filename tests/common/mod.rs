@@ -0,0 +1,82 @@
+// each test crate uses its own share of these
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use file_browser::{handle_key, Action, Config, Side, State};
+
+pub fn press(state: &mut State, code: KeyCode) -> Option<Action> {
+    handle_key(state, KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+pub fn ctrl(state: &mut State, c: char) -> Option<Action> {
+    handle_key(
+        state,
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL),
+    )
+}
+
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("file-browser-{}-{}", name, std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A state with both panes showing `dir` once the listings are in.
+pub async fn showing(dir: &Path) -> State {
+    showing_with(Config::default(), dir, dir).await
+}
+
+pub async fn showing_with(config: Config, left: &Path, right: &Path) -> State {
+    let left = left.to_string_lossy().to_string();
+    let right = right.to_string_lossy().to_string();
+    let mut state = State::new(config, &left, &right);
+    state.load_pane(Side::Left, left.clone());
+    state.load_pane(Side::Right, right);
+    for _ in 0..500 {
+        state.receive_updates();
+        if !state.pane(Side::Left).is_loading() && !state.pane(Side::Right).is_loading() {
+            return state;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{} was not listed", left);
+}
+
+/// Waits for the listings requested by the last key press, in either pane.
+pub async fn listed(state: &mut State) {
+    for _ in 0..500 {
+        state.receive_updates();
+        if !state.pane(Side::Left).is_loading() && !state.pane(Side::Right).is_loading() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{} was not listed", state.active_pane().path());
+}
+
+/// Waits for the copy started by the last key press to finish.
+pub async fn copied(state: &mut State) {
+    for _ in 0..500 {
+        state.receive_updates();
+        if state
+            .status_message()
+            .is_some_and(|m| m.starts_with("Copied"))
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the copy did not finish");
+}
+
+pub fn selected_name(state: &State) -> String {
+    let path = state.active_pane().selected_path().unwrap();
+    path.file_name().unwrap().to_string_lossy().to_string()
+}
@@ -0,0 +1,406 @@
+mod common;
+
+use std::{fs, path::Path, time::Duration};
+
+use common::*;
+use crossterm::event::{KeyCode, KeyModifiers};
+use file_browser::{draw, Action, Config, Confirmation, Field, Mode, Side, State};
+use ratatui::{backend::TestBackend, Terminal};
+
+#[test]
+fn help_opens_and_closes() {
+    let mut state = State::new(Config::default(), "/", "/");
+    press(&mut state, KeyCode::Char('?'));
+    assert_eq!(state.mode(), Mode::Help);
+    press(&mut state, KeyCode::Char('?'));
+    assert_eq!(state.mode(), Mode::Normal);
+}
+
+#[test]
+fn help_shows_the_remapped_keys() {
+    let mut config = Config::default();
+    config
+        .keymap
+        .insert((KeyCode::Char('w'), KeyModifiers::NONE), Action::Swap);
+    config
+        .keymap
+        .insert((KeyCode::Char('x'), KeyModifiers::NONE), Action::Mirror);
+    let mut state = State::new(config, "/", "/");
+    press(&mut state, KeyCode::Char('?'));
+    let mut terminal = Terminal::new(TestBackend::new(100, 60)).unwrap();
+    draw(&mut terminal, &mut state).unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let lines: Vec<String> = (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.get(x, y).symbol.clone())
+                .collect()
+        })
+        .collect();
+    let row = |description: &str| {
+        lines
+            .iter()
+            .find(|line| line.contains(description))
+            .unwrap_or_else(|| panic!("no {:?} row", description))
+            .clone()
+    };
+    assert!(row("swap the panes").contains(" w "));
+    assert!(row("open this directory in the other pane").contains(" =, x "));
+    assert!(row("first / last entry").contains(" gg / G "));
+}
+
+#[test]
+fn prompts_close_on_escape_and_ctrl_c() {
+    let mut state = State::new(Config::default(), "/", "/");
+    press(&mut state, KeyCode::Char(':'));
+    assert_eq!(state.mode(), Mode::Edit(Field::Command));
+    press(&mut state, KeyCode::Esc);
+    assert_eq!(state.mode(), Mode::Normal);
+
+    press(&mut state, KeyCode::Char('n'));
+    assert_eq!(state.mode(), Mode::Edit(Field::NewDir));
+    assert_eq!(ctrl(&mut state, 'c'), None);
+    assert_eq!(state.mode(), Mode::Normal);
+}
+
+#[test]
+fn typing_edits_the_input() {
+    let mut state = State::new(Config::default(), "/", "/");
+    press(&mut state, KeyCode::Char(':'));
+    for c in "mkd".chars() {
+        press(&mut state, KeyCode::Char(c));
+    }
+    press(&mut state, KeyCode::Backspace);
+    assert_eq!(state.input(), "mk");
+    // held Ctrl doesn't type the letter
+    ctrl(&mut state, 'x');
+    assert_eq!(state.input(), "mk");
+}
+
+#[test]
+fn path_prompts_switch_the_active_pane() {
+    let mut state = State::new(Config::default(), "/", "/");
+    press(&mut state, KeyCode::Char('L'));
+    assert_eq!(state.active(), Side::Right);
+    assert_eq!(state.mode(), Mode::Edit(Field::RightPath));
+    press(&mut state, KeyCode::Esc);
+    press(&mut state, KeyCode::Tab);
+    assert_eq!(state.active(), Side::Left);
+}
+
+#[test]
+fn quitting_is_left_to_run() {
+    let mut state = State::new(Config::default(), "/", "/");
+    assert_eq!(press(&mut state, KeyCode::Char('q')), Some(Action::Quit));
+    assert_eq!(ctrl(&mut state, 'c'), Some(Action::Quit));
+    assert_eq!(state.mode(), Mode::Normal);
+}
+
+#[tokio::test]
+async fn navigation_stops_at_the_ends() {
+    let dir = temp_dir("navigation");
+    for name in ["a", "b", "c"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    let mut state = showing(&dir).await;
+
+    assert_eq!(selected_name(&state), "a");
+    press(&mut state, KeyCode::Char('k'));
+    assert_eq!(selected_name(&state), "a");
+    press(&mut state, KeyCode::Char('j'));
+    assert_eq!(selected_name(&state), "b");
+    press(&mut state, KeyCode::Char('G'));
+    assert_eq!(selected_name(&state), "c");
+    press(&mut state, KeyCode::Char('j'));
+    assert_eq!(selected_name(&state), "c");
+    press(&mut state, KeyCode::Char('g'));
+    press(&mut state, KeyCode::Char('g'));
+    assert_eq!(selected_name(&state), "a");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn entering_files_is_left_to_run() {
+    let dir = temp_dir("enter");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("file"), "").unwrap();
+    let mut state = showing(&dir).await;
+
+    // directories come first
+    assert_eq!(selected_name(&state), "sub");
+    press(&mut state, KeyCode::Char('j'));
+    assert_eq!(press(&mut state, KeyCode::Enter), Some(Action::Open));
+    press(&mut state, KeyCode::Char('k'));
+    assert_eq!(press(&mut state, KeyCode::Enter), None);
+    assert!(state.active_pane().is_loading());
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn new_directories_are_highlighted_once_listed() {
+    let dir = temp_dir("new-dir");
+    for name in ["a", "c"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    let mut state = showing(&dir).await;
+
+    press(&mut state, KeyCode::Char('n'));
+    press(&mut state, KeyCode::Char('b'));
+    press(&mut state, KeyCode::Enter);
+    // the directory is read again in the background, not while the key is handled
+    assert!(state.active_pane().is_loading());
+    listed(&mut state).await;
+    assert_eq!(selected_name(&state), "b");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn deleting_needs_an_explicit_yes() {
+    let dir = temp_dir("delete");
+    for name in ["a", "b"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    let mut state = showing(&dir).await;
+
+    press(&mut state, KeyCode::Char(' '));
+    press(&mut state, KeyCode::Char(' '));
+    press(&mut state, KeyCode::Char('d'));
+    assert_eq!(state.mode(), Mode::Confirm(Confirmation::Delete));
+    press(&mut state, KeyCode::Enter);
+    assert_eq!(state.mode(), Mode::Normal);
+    assert!(dir.join("a").exists() && dir.join("b").exists());
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn going_back_shows_changes_made_meanwhile() {
+    let dir = temp_dir("revisit");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub").join("old"), "").unwrap();
+    let mut state = showing(&dir).await;
+
+    press(&mut state, KeyCode::Char('l'));
+    listed(&mut state).await;
+    assert_eq!(state.active_pane().visible().len(), 1);
+    // the directory was listed before and hasn't changed since
+    press(&mut state, KeyCode::Char('h'));
+    assert!(!state.active_pane().is_loading());
+    fs::write(dir.join("sub").join("new"), "").unwrap();
+    press(&mut state, KeyCode::Char('l'));
+    listed(&mut state).await;
+    assert_eq!(state.active_pane().visible().len(), 2);
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn refreshing_goes_past_the_cache() {
+    let dir = temp_dir("rewritten");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub").join("file"), "a").unwrap();
+    let mut state = showing(&dir).await;
+
+    press(&mut state, KeyCode::Char('l'));
+    listed(&mut state).await;
+    press(&mut state, KeyCode::Char('h'));
+    listed(&mut state).await;
+    // writing to a file leaves its directory's mtime alone
+    fs::write(dir.join("sub").join("file"), "abc").unwrap();
+    press(&mut state, KeyCode::Char('l'));
+    assert!(!state.active_pane().is_loading());
+    press(&mut state, KeyCode::Char('r'));
+    listed(&mut state).await;
+    assert_eq!(state.active_pane().visible()[0].size, 3);
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinked_directories_are_followed_when_asked() {
+    let dir = temp_dir("symlink");
+    fs::create_dir_all(dir.join("target").join("inner")).unwrap();
+    std::os::unix::fs::symlink(dir.join("target").join("inner"), dir.join("link")).unwrap();
+    let mut state = showing(&dir).await;
+
+    // entries are sorted, so the link comes before the target
+    assert_eq!(selected_name(&state), "link");
+    press(&mut state, KeyCode::Char('l'));
+    listed(&mut state).await;
+    assert_eq!(Path::new(state.active_pane().path()), dir.join("link"));
+    press(&mut state, KeyCode::Char('h'));
+    listed(&mut state).await;
+
+    press(&mut state, KeyCode::Char('F'));
+    press(&mut state, KeyCode::Char('l'));
+    listed(&mut state).await;
+    let inner = dir.join("target").join("inner").canonicalize().unwrap();
+    assert_eq!(Path::new(state.active_pane().path()), inner);
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn renaming_never_replaces_or_moves_out() {
+    let dir = temp_dir("rename");
+    for name in ["a", "b"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    let mut state = showing(&dir).await;
+
+    for name in ["b", "", "..", "../a"] {
+        assert_eq!(selected_name(&state), "a");
+        press(&mut state, KeyCode::Char('R'));
+        press(&mut state, KeyCode::Backspace);
+        for c in name.chars() {
+            press(&mut state, KeyCode::Char(c));
+        }
+        press(&mut state, KeyCode::Enter);
+        assert!(state
+            .status_message()
+            .unwrap()
+            .starts_with("Failed to rename"));
+    }
+    assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b");
+    assert!(!dir.parent().unwrap().join("a").exists());
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn refreshing_lists_in_the_background() {
+    let dir = temp_dir("refresh");
+    fs::write(dir.join("a"), "").unwrap();
+    let mut state = showing(&dir).await;
+
+    fs::write(dir.join("b"), "").unwrap();
+    press(&mut state, KeyCode::Char('r'));
+    assert!(state.active_pane().is_loading());
+    listed(&mut state).await;
+    assert_eq!(state.active_pane().visible().len(), 2);
+    assert_eq!(selected_name(&state), "a");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn undoing_a_copy_keeps_what_changed_since() {
+    let left = temp_dir("undo-from");
+    let right = temp_dir("undo-to");
+    fs::create_dir(left.join("dir")).unwrap();
+    fs::write(left.join("file"), "").unwrap();
+    let mut state = showing_with(Config::default(), &left, &right).await;
+
+    // the file is copied and undone as it is
+    press(&mut state, KeyCode::Char('j'));
+    press(&mut state, KeyCode::Char('c'));
+    copied(&mut state).await;
+    press(&mut state, KeyCode::Char('u'));
+    assert!(!right.join("file").exists());
+
+    // something is added to the copied directory before undoing
+    press(&mut state, KeyCode::Char('k'));
+    press(&mut state, KeyCode::Char('c'));
+    copied(&mut state).await;
+    fs::write(right.join("dir").join("new"), "").unwrap();
+    press(&mut state, KeyCode::Char('u'));
+    assert!(state
+        .status_message()
+        .unwrap()
+        .starts_with("Failed to undo"));
+    assert!(right.join("dir").join("new").exists());
+
+    fs::remove_dir_all(&left).ok();
+    fs::remove_dir_all(&right).ok();
+}
+
+#[tokio::test]
+async fn swapping_keeps_a_size_running() {
+    let left = temp_dir("swap-left");
+    let right = temp_dir("swap-right");
+    fs::create_dir(left.join("sub")).unwrap();
+    fs::write(left.join("sub").join("file"), "abc").unwrap();
+    let mut state = showing_with(Config::default(), &left, &right).await;
+
+    press(&mut state, KeyCode::Char('S'));
+    press(&mut state, KeyCode::Char('x'));
+    for _ in 0..500 {
+        state.receive_updates();
+        if state
+            .status_message()
+            .is_some_and(|m| m.starts_with("sub:"))
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(state.status_message(), Some("sub: 3B"));
+
+    fs::remove_dir_all(&left).ok();
+    fs::remove_dir_all(&right).ok();
+}
+
+#[tokio::test]
+async fn reopened_path_prompts_forget_the_picked_suggestion() {
+    let dir = temp_dir("suggestions");
+    fs::create_dir(dir.join("sub")).unwrap();
+    let config = Config {
+        builtin_matcher: true,
+        ..Config::default()
+    };
+    let mut state = showing_with(config, &dir, &dir).await;
+
+    press(&mut state, KeyCode::Char('H'));
+    for c in format!("{}/su", dir.display()).chars() {
+        press(&mut state, KeyCode::Char(c));
+    }
+    for _ in 0..500 {
+        state.receive_updates();
+        if !state.pane(Side::Left).suggestions().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    press(&mut state, KeyCode::Down);
+    press(&mut state, KeyCode::Esc);
+    assert!(state.pane(Side::Left).suggestions().is_empty());
+
+    // Enter on the empty input has nothing to go to
+    press(&mut state, KeyCode::Char('H'));
+    press(&mut state, KeyCode::Enter);
+    assert!(!state.active_pane().is_loading());
+    assert_eq!(Path::new(state.active_pane().path()), dir);
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinks_are_copied_as_links() {
+    let left = temp_dir("links-from");
+    let right = temp_dir("links-to");
+    fs::create_dir(left.join("dir")).unwrap();
+    fs::write(left.join("dir").join("file"), "abc").unwrap();
+    // a cycle, which followed would never end
+    std::os::unix::fs::symlink("..", left.join("dir").join("up")).unwrap();
+    let mut state = showing_with(Config::default(), &left, &right).await;
+
+    press(&mut state, KeyCode::Char('c'));
+    copied(&mut state).await;
+    let up = right.join("dir").join("up");
+    assert!(fs::symlink_metadata(&up).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_link(&up).unwrap(), Path::new(".."));
+    assert_eq!(
+        fs::read_to_string(right.join("dir").join("file")).unwrap(),
+        "abc"
+    );
+
+    fs::remove_dir_all(&left).ok();
+    fs::remove_dir_all(&right).ok();
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use common::*;
+use crossterm::event::KeyCode;
+use file_browser::{draw, run_fzf_query, CaseMode, Config, Side, State, SuggestionOptions};
+use ratatui::{backend::TestBackend, Terminal};
+
+const NAMES: [&str; 4] = ["a b", "\"quoted\"", "emoji 🚀.txt", "heart ❤️.txt"];
+
+/// A directory holding a three byte file for each of `NAMES`.
+fn odd_dir(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    for name in NAMES {
+        fs::write(dir.join(name), "abc").unwrap();
+    }
+    dir
+}
+
+fn names_in(dir: &Path) -> BTreeSet<String> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+fn listed_names(state: &State, side: Side) -> BTreeSet<String> {
+    state
+        .pane(side)
+        .visible()
+        .iter()
+        .map(|entry| {
+            entry
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect()
+}
+
+fn expected(names: &[&str]) -> BTreeSet<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[tokio::test]
+async fn odd_names_are_listed_as_they_are() {
+    let dir = odd_dir("listed");
+    let state = showing(&dir).await;
+
+    assert_eq!(listed_names(&state, Side::Left), expected(&NAMES));
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn odd_names_can_be_renamed() {
+    let dir = odd_dir("renamed");
+    let mut state = showing(&dir).await;
+
+    while selected_name(&state) != "a b" {
+        press(&mut state, KeyCode::Char('j'));
+    }
+    press(&mut state, KeyCode::Char('R'));
+    assert_eq!(state.input(), "a b");
+    for c in " 'c' 🚀".chars() {
+        press(&mut state, KeyCode::Char(c));
+    }
+    press(&mut state, KeyCode::Enter);
+    listed(&mut state).await;
+
+    assert_eq!(
+        names_in(&dir),
+        expected(&["a b 'c' 🚀", "\"quoted\"", "emoji 🚀.txt", "heart ❤️.txt"])
+    );
+    assert_eq!(selected_name(&state), "a b 'c' 🚀");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn new_directories_stay_in_the_directory() {
+    let parent = temp_dir("new-dirs");
+    let dir = parent.join("inner");
+    fs::create_dir(&dir).unwrap();
+    let mut state = showing(&dir).await;
+
+    // an empty name once reported that the directory itself already exists
+    for name in ["../escaped", "a/b", "..", ".", ""] {
+        press(&mut state, KeyCode::Char('n'));
+        for c in name.chars() {
+            press(&mut state, KeyCode::Char(c));
+        }
+        press(&mut state, KeyCode::Enter);
+        assert!(
+            state
+                .status_message()
+                .is_some_and(|m| m.starts_with("Cannot create")),
+            "{:?} gave {:?}",
+            name,
+            state.status_message()
+        );
+    }
+    press(&mut state, KeyCode::Char('n'));
+    press(&mut state, KeyCode::Enter);
+    assert!(!state
+        .status_message()
+        .unwrap_or_default()
+        .contains("already exists"));
+
+    assert_eq!(names_in(&parent), expected(&["inner"]));
+    assert!(names_in(&dir).is_empty());
+
+    fs::remove_dir_all(&parent).ok();
+}
+
+#[tokio::test]
+async fn odd_names_are_copied_and_deleted() {
+    let left = odd_dir("copied-from");
+    let right = temp_dir("copied-to");
+    let config = Config {
+        permanent_delete: true,
+        ..Config::default()
+    };
+    let mut state = showing_with(config, &left, &right).await;
+
+    for _ in NAMES {
+        press(&mut state, KeyCode::Char(' '));
+    }
+    press(&mut state, KeyCode::Char('c'));
+    copied(&mut state).await;
+    listed(&mut state).await;
+    assert_eq!(names_in(&right), expected(&NAMES));
+    assert_eq!(listed_names(&state, Side::Right), expected(&NAMES));
+
+    press(&mut state, KeyCode::Tab);
+    for _ in NAMES {
+        press(&mut state, KeyCode::Char(' '));
+    }
+    press(&mut state, KeyCode::Char('d'));
+    press(&mut state, KeyCode::Char('y'));
+    assert!(names_in(&right).is_empty());
+    assert_eq!(names_in(&left), expected(&NAMES));
+
+    fs::remove_dir_all(&left).ok();
+    fs::remove_dir_all(&right).ok();
+}
+
+#[tokio::test]
+async fn fzf_takes_the_query_as_a_path() {
+    let dir = temp_dir("fzf");
+    let odd = dir.join("it's a \"dir\" 🚀!");
+    fs::create_dir(&odd).unwrap();
+    let options = SuggestionOptions {
+        max_depth: 1,
+        case: CaseMode::Smart,
+        files: false,
+    };
+
+    let query = odd.to_string_lossy().to_string();
+    match run_fzf_query(&query, options).await {
+        Ok(suggestions) => assert_eq!(suggestions, vec![query]),
+        Err(e) if e.to_string().contains("not found") => eprintln!("skipped: {}", e),
+        Err(e) => panic!("{}", e),
+    }
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn wide_names_keep_the_columns_aligned() {
+    let dir = odd_dir("aligned");
+    let mut state = showing(&dir).await;
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    draw(&mut terminal, &mut state).unwrap();
+
+    // the column each "3B" size starts in, one per row that has one in the left pane
+    let buffer = terminal.backend().buffer();
+    let size_columns: Vec<u16> = (0..buffer.area.height)
+        .filter_map(|y| {
+            (0..buffer.area.width / 2)
+                .find(|&x| buffer.get(x, y).symbol == "3" && buffer.get(x + 1, y).symbol == "B")
+        })
+        .collect();
+    assert_eq!(size_columns.len(), NAMES.len());
+    assert!(size_columns.iter().all(|&x| x == size_columns[0]));
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn names_show_at_the_smallest_size() {
+    let dir = odd_dir("smallest");
+    let mut state = showing(&dir).await;
+    // select a file so the preview is shown, and narrow the left pane as far as it goes
+    press(&mut state, KeyCode::Char('j'));
+    for _ in 0..10 {
+        press(&mut state, KeyCode::Char('<'));
+    }
+    let mut terminal = Terminal::new(TestBackend::new(36, 12)).unwrap();
+    draw(&mut terminal, &mut state).unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let text: String = (0..buffer.area.height)
+        .flat_map(|y| (0..buffer.area.width).map(move |x| (x, y)))
+        .map(|(x, y)| buffer.get(x, y).symbol.clone())
+        .collect();
+    assert!(!text.contains("Terminal too small"));
+    // the left pane's selection moved, the right one's didn't
+    assert!(text.contains(">> a b"), "{}", text);
+    assert!(text.contains(">> \"quoted\""), "{}", text);
+
+    fs::remove_dir_all(&dir).ok();
+}